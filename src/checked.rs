@@ -0,0 +1,221 @@
+//! A streaming iterator which checks that another is used and implemented correctly.
+
+use super::{DoubleEndedStreamingIterator, StreamingIterator};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CheckedState {
+    Start,
+    Active,
+    Ended,
+}
+
+/// A streaming iterator which panics when the `advance`/`get` protocol is broken, either by its
+/// caller or by the iterator it wraps.
+#[derive(Clone, Debug)]
+pub struct Checked<I> {
+    it: I,
+    state: CheckedState,
+}
+
+impl<I> Checked<I>
+where
+    I: StreamingIterator,
+{
+    #[inline]
+    pub(crate) fn new(it: I) -> Checked<I> {
+        Checked {
+            it,
+            state: CheckedState::Start,
+        }
+    }
+
+    /// Checks the result of advancing the inner iterator from the front or the back, given its
+    /// size hint beforehand.
+    fn check_advance(&mut self, method: &str, hint: (usize, Option<usize>)) {
+        match self.it.get() {
+            Some(_) => {
+                if hint.1 == Some(0) {
+                    panic!(
+                        "`{}` produced an element after `size_hint` reported an upper bound of 0",
+                        method
+                    );
+                }
+                self.state = CheckedState::Active;
+            }
+            None => {
+                if hint.0 > 0 {
+                    panic!(
+                        "`{}` reached the end after `size_hint` reported a lower bound of {}",
+                        method, hint.0
+                    );
+                }
+                self.state = CheckedState::Ended;
+            }
+        }
+    }
+
+    fn check_not_ended(&self, method: &str) {
+        if self.state == CheckedState::Ended {
+            panic!(
+                "`{}` called after a streaming iterator reached its end; use `fuse` to make this \
+                 well defined",
+                method
+            );
+        }
+    }
+
+    fn hint(&self) -> (usize, Option<usize>) {
+        let hint = self.it.size_hint();
+        if let (lower, Some(upper)) = hint {
+            if lower > upper {
+                panic!(
+                    "`size_hint` returned a lower bound of {} above its upper bound of {}",
+                    lower, upper
+                );
+            }
+        }
+        hint
+    }
+}
+
+impl<I> StreamingIterator for Checked<I>
+where
+    I: StreamingIterator,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        self.check_not_ended("advance");
+        let hint = self.hint();
+        self.it.advance();
+        self.check_advance("advance", hint);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&I::Item> {
+        if self.state == CheckedState::Start {
+            panic!("`get` called on a streaming iterator before `advance`");
+        }
+        let item = self.it.get();
+        if item.is_some() != (self.state == CheckedState::Active) {
+            panic!("`get` returned a different result without the iterator being advanced");
+        }
+        item
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.hint()
+    }
+}
+
+impl<I> DoubleEndedStreamingIterator for Checked<I>
+where
+    I: DoubleEndedStreamingIterator,
+{
+    #[inline]
+    fn advance_back(&mut self) {
+        self.check_not_ended("advance_back");
+        let hint = self.hint();
+        self.it.advance_back();
+        self.check_advance("advance_back", hint);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    /// A streaming iterator with a fixed, possibly wrong, size hint.
+    struct Hinted<I> {
+        it: I,
+        hint: (usize, Option<usize>),
+    }
+
+    impl<I: StreamingIterator> StreamingIterator for Hinted<I> {
+        type Item = I::Item;
+
+        fn advance(&mut self) {
+            self.it.advance()
+        }
+
+        fn get(&self) -> Option<&I::Item> {
+            self.it.get()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.hint
+        }
+    }
+
+    #[test]
+    fn checked() {
+        let items = [0, 1, 2];
+        let mut it = convert(items.iter().cloned()).checked();
+        assert_eq!(it.next(), Some(&0));
+        assert_eq!(it.next_back(), Some(&2));
+        assert_eq!(it.next(), Some(&1));
+        assert_eq!(it.get(), Some(&1));
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.get(), None);
+
+        let mut it = convert(items.iter().cloned()).checked().fuse();
+        while it.next().is_some() {}
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "`get` called on a streaming iterator before `advance`")]
+    fn get_before_advance() {
+        let items = [0];
+        convert(items.iter()).checked().get();
+    }
+
+    #[test]
+    #[should_panic(expected = "`advance_back` called after a streaming iterator reached its end")]
+    fn advance_after_end() {
+        let items = [0];
+        let mut it = convert(items.iter()).checked();
+        while it.next().is_some() {}
+        it.advance_back();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "`advance` reached the end after `size_hint` reported a lower bound of 3"
+    )]
+    fn broken_lower_bound() {
+        let items = [0, 1];
+        let it = Hinted {
+            it: convert(items.iter()),
+            hint: (3, None),
+        };
+        it.checked().count();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "`advance` produced an element after `size_hint` reported an upper bound of 0"
+    )]
+    fn broken_upper_bound() {
+        let items = [0, 1];
+        let it = Hinted {
+            it: convert(items.iter()),
+            hint: (0, Some(0)),
+        };
+        it.checked().advance();
+    }
+
+    #[test]
+    #[should_panic(expected = "`size_hint` returned a lower bound of 2 above its upper bound of 1")]
+    fn invalid_hint() {
+        let items = [0];
+        let it = Hinted {
+            it: convert(items.iter()),
+            hint: (2, Some(1)),
+        };
+        it.checked().size_hint();
+    }
+}
@@ -0,0 +1,346 @@
+//! Conversions into streaming iterators and collections built from them.
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::ToOwned;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::{self, Vec};
+use core::iter;
+use core::ops::Deref;
+use core::option;
+use core::slice;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+
+#[cfg(feature = "alloc")]
+use super::{convert, Convert};
+use super::{convert_ref, ConvertRef, StreamingIterator};
+
+/// Conversion into a `StreamingIterator`.
+///
+/// This is the streaming counterpart of `IntoIterator`, allowing functions to accept streaming
+/// iterators, slices and vectors alike.
+///
+/// ```
+/// # use streaming_iterator::{IntoStreamingIterator, StreamingIterator};
+/// fn total<I: IntoStreamingIterator<Item = u32>>(items: I) -> u32 {
+///     items.into_streaming_iter().fold(0, |acc, &i| acc + i)
+/// }
+///
+/// assert_eq!(total(&[1, 2, 3][..]), 6);
+/// # #[cfg(feature = "alloc")]
+/// assert_eq!(total(vec![1, 2, 3]), 6);
+/// ```
+pub trait IntoStreamingIterator {
+    /// The type of the elements being iterated over.
+    type Item: ?Sized;
+
+    /// The streaming iterator this value is converted into.
+    type IntoStreamingIter: StreamingIterator<Item = Self::Item>;
+
+    /// Creates a streaming iterator from this value.
+    fn into_streaming_iter(self) -> Self::IntoStreamingIter;
+}
+
+impl<I> IntoStreamingIterator for I
+where
+    I: StreamingIterator,
+{
+    type Item = I::Item;
+    type IntoStreamingIter = I;
+
+    #[inline]
+    fn into_streaming_iter(self) -> I {
+        self
+    }
+}
+
+impl<'a, T> IntoStreamingIterator for &'a [T] {
+    type Item = T;
+    type IntoStreamingIter = ConvertRef<'a, slice::Iter<'a, T>, T>;
+
+    #[inline]
+    fn into_streaming_iter(self) -> Self::IntoStreamingIter {
+        convert_ref(self)
+    }
+}
+
+/// A string slice converts into a streaming iterator yielding it as its only element.
+impl<'a> IntoStreamingIterator for &'a str {
+    type Item = str;
+    type IntoStreamingIter = ConvertRef<'a, option::IntoIter<&'a str>, str>;
+
+    #[inline]
+    fn into_streaming_iter(self) -> Self::IntoStreamingIter {
+        convert_ref(Some(self))
+    }
+}
+
+/// A slice of values such as `String`s, which converts into a streaming iterator over the targets
+/// they dereference to.
+///
+/// `&[String]` itself converts into a streaming iterator over `String`s. Coherence rules out a
+/// second implementation for it yielding `str`, since it would overlap with the implementation
+/// for every `&[T]`, so the slice is wrapped instead.
+///
+/// ```
+/// # use streaming_iterator::{Derefs, IntoStreamingIterator, StreamingIterator};
+/// fn longest<I: IntoStreamingIterator<Item = str>>(words: I) -> usize {
+///     words.into_streaming_iter().fold(0, |max, w| max.max(w.len()))
+/// }
+///
+/// let words = vec!["pear".to_owned(), "apple".to_owned()];
+/// assert_eq!(longest(Derefs(&words)), 5);
+/// assert_eq!(longest("fig"), 3);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Derefs<'a, T: 'a>(pub &'a [T]);
+
+impl<'a, T> IntoStreamingIterator for Derefs<'a, T>
+where
+    T: Deref,
+{
+    type Item = T::Target;
+    type IntoStreamingIter =
+        ConvertRef<'a, iter::Map<slice::Iter<'a, T>, fn(&'a T) -> &'a T::Target>, T::Target>;
+
+    #[inline]
+    fn into_streaming_iter(self) -> Self::IntoStreamingIter {
+        convert_ref(
+            self.0
+                .iter()
+                .map(Deref::deref as fn(&'a T) -> &'a T::Target),
+        )
+    }
+}
+
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> IntoStreamingIterator for Vec<T> {
+    type Item = T;
+    type IntoStreamingIter = Convert<vec::IntoIter<T>>;
+
+    #[inline]
+    fn into_streaming_iter(self) -> Self::IntoStreamingIter {
+        convert(self)
+    }
+}
+
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<'a, T> IntoStreamingIterator for &'a Vec<T> {
+    type Item = T;
+    type IntoStreamingIter = ConvertRef<'a, slice::Iter<'a, T>, T>;
+
+    #[inline]
+    fn into_streaming_iter(self) -> Self::IntoStreamingIter {
+        convert_ref(self)
+    }
+}
+
+/// Conversion from a `StreamingIterator`.
+///
+/// This is the streaming counterpart of `FromIterator`, used by `StreamingIterator::collect`.
+/// Since elements are only borrowed, implementations copy whatever they keep out of each one.
+pub trait FromStreamingIterator<A: ?Sized> {
+    /// Creates a value from the elements of a streaming iterator.
+    fn from_streaming_iter<I>(iter: I) -> Self
+    where
+        I: IntoStreamingIterator<Item = A>;
+}
+
+/// Concatenates string slices.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl FromStreamingIterator<str> for String {
+    #[inline]
+    fn from_streaming_iter<I>(iter: I) -> String
+    where
+        I: IntoStreamingIterator<Item = str>,
+    {
+        iter.into_streaming_iter()
+            .fold(String::new(), |mut buf, s| {
+                buf.push_str(s);
+                buf
+            })
+    }
+}
+
+/// Concatenates byte slices.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl FromStreamingIterator<[u8]> for Vec<u8> {
+    #[inline]
+    fn from_streaming_iter<I>(iter: I) -> Vec<u8>
+    where
+        I: IntoStreamingIterator<Item = [u8]>,
+    {
+        iter.into_streaming_iter().fold(vec![], |mut buf, bytes| {
+            buf.extend_from_slice(bytes);
+            buf
+        })
+    }
+}
+
+/// Collects a clone of each element.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> FromStreamingIterator<T> for Vec<T>
+where
+    T: Clone,
+{
+    #[inline]
+    fn from_streaming_iter<I>(iter: I) -> Vec<T>
+    where
+        I: IntoStreamingIterator<Item = T>,
+    {
+        collect_owned(iter)
+    }
+}
+
+/// Collects an owned copy of each string slice.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl FromStreamingIterator<str> for Vec<String> {
+    #[inline]
+    fn from_streaming_iter<I>(iter: I) -> Vec<String>
+    where
+        I: IntoStreamingIterator<Item = str>,
+    {
+        collect_owned(iter)
+    }
+}
+
+/// Collects an owned copy of each slice.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> FromStreamingIterator<[T]> for Vec<Vec<T>>
+where
+    T: Clone,
+{
+    #[inline]
+    fn from_streaming_iter<I>(iter: I) -> Vec<Vec<T>>
+    where
+        I: IntoStreamingIterator<Item = [T]>,
+    {
+        collect_owned(iter)
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn collect_owned<I>(iter: I) -> Vec<<I::Item as ToOwned>::Owned>
+where
+    I: IntoStreamingIterator,
+    I::Item: ToOwned,
+{
+    let it = iter.into_streaming_iter();
+    let buf = Vec::with_capacity(it.size_hint().0);
+    it.fold(buf, |mut buf, item| {
+        buf.push(item.to_owned());
+        buf
+    })
+}
+
+/// Collects a copy of each key-value pair.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+impl<K, V, S> FromStreamingIterator<(K, V)> for HashMap<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    S: BuildHasher + Default,
+{
+    #[inline]
+    fn from_streaming_iter<I>(iter: I) -> HashMap<K, V, S>
+    where
+        I: IntoStreamingIterator<Item = (K, V)>,
+    {
+        let it = iter.into_streaming_iter();
+        let mut map = HashMap::with_capacity_and_hasher(it.size_hint().0, S::default());
+        it.for_each(|(k, v)| {
+            map.insert(k.clone(), v.clone());
+        });
+        map
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "alloc")]
+    use alloc::borrow::ToOwned;
+    #[cfg(feature = "alloc")]
+    use alloc::string::String;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+
+    use super::super::*;
+
+    fn sum<I: IntoStreamingIterator<Item = i32>>(items: I) -> i32 {
+        items.into_streaming_iter().fold(0, |acc, &i| acc + i)
+    }
+
+    #[test]
+    fn into_streaming_iter() {
+        let items = [1, 2, 3];
+        assert_eq!(sum(&items[..]), 6);
+        assert_eq!(sum(convert(items.iter().cloned()).skip(1)), 5);
+
+        let mut it = "abc".into_streaming_iter();
+        assert_eq!(it.next(), Some("abc"));
+        assert_eq!(it.next(), None);
+
+        let words = ["ab", "c"];
+        let mut it = Derefs(&words[..]).into_streaming_iter();
+        assert_eq!(it.next(), Some("ab"));
+        assert_eq!(it.next_back(), Some("c"));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn into_streaming_iter_vec() {
+        let items = vec![1, 2, 3];
+        assert_eq!(sum(&items), 6);
+        assert_eq!(sum(items), 6);
+
+        let strings = vec!["ab".to_owned(), "c".to_owned()];
+        let joined: String = Derefs(&strings).into_streaming_iter().collect();
+        assert_eq!(joined, "abc");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn collect() {
+        let words = ["ab", "c", "de"];
+        let joined: String = convert_ref(words.iter().cloned()).collect();
+        assert_eq!(joined, "abcde");
+        let owned: Vec<String> = convert_ref(words.iter().cloned()).collect();
+        assert_eq!(owned, words);
+
+        let chunks = [&b"ab"[..], b"c"];
+        let bytes: Vec<u8> = convert_ref(chunks.iter().cloned()).collect();
+        assert_eq!(bytes, b"abc");
+        let owned: Vec<Vec<u8>> = convert_ref(chunks.iter().cloned()).collect();
+        assert_eq!(owned, [b"ab".to_vec(), b"c".to_vec()]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn collect_hash_map() {
+        use std::collections::HashMap;
+
+        let pairs = [("a", 1), ("b", 2), ("a", 3)];
+        let map: HashMap<_, _> = pairs[..].into_streaming_iter().collect();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["a"], 3);
+    }
+}
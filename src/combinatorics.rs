@@ -0,0 +1,1401 @@
+//! Streaming iterators over permutations, combinations and related sequences.
+
+use alloc::vec::Vec;
+use core::cmp;
+use core::mem;
+
+use super::{DoubleEndedStreamingIterator, StreamingIterator};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum End {
+    Front,
+    Back,
+}
+
+fn factorial(n: usize) -> Option<usize> {
+    (1..n + 1).try_fold(1usize, |acc, i| acc.checked_mul(i))
+}
+
+fn binomial(n: usize, k: usize) -> Option<usize> {
+    if k > n {
+        return Some(0);
+    }
+    let k = cmp::min(k, n - k);
+    let mut c = 1u128;
+    for i in 0..k {
+        // c is C(n, i + 1) after each step, so the division is exact
+        c = c.checked_mul((n - i) as u128)? / (i as u128 + 1);
+    }
+    if c > usize::MAX as u128 {
+        None
+    } else {
+        Some(c as usize)
+    }
+}
+
+/// Steps `perm` to the lexicographically next (or previous) permutation, applying the same swaps
+/// to `items` if `mirror` is set.
+fn step_permutation<T>(perm: &mut [usize], items: &mut [T], mirror: bool, forward: bool) -> bool {
+    let before = |a: usize, b: usize| if forward { a < b } else { a > b };
+
+    let n = perm.len();
+    let mut i = n;
+    loop {
+        if i < 2 {
+            return false;
+        }
+        i -= 1;
+        if before(perm[i - 1], perm[i]) {
+            break;
+        }
+    }
+    let mut j = n - 1;
+    while !before(perm[i - 1], perm[j]) {
+        j -= 1;
+    }
+
+    perm.swap(i - 1, j);
+    perm[i..].reverse();
+    if mirror {
+        items.swap(i - 1, j);
+        items[i..].reverse();
+    }
+    true
+}
+
+/// Rearranges `items`, currently ordered as described by `cur`, into the order described by
+/// `target`, using only swaps.
+fn rearrange<T>(items: &mut [T], cur: &mut [usize], pos: &mut [usize], target: &[usize]) {
+    for (i, &c) in cur.iter().enumerate() {
+        pos[c] = i;
+    }
+    for (i, &want) in target.iter().enumerate() {
+        let p = pos[want];
+        if p != i {
+            items.swap(i, p);
+            cur.swap(i, p);
+            pos[cur[p]] = p;
+            pos[want] = i;
+        }
+    }
+}
+
+/// Creates a streaming iterator over all permutations of `items`, in lexicographic order of
+/// their positions.
+///
+/// The items are permuted in place in a single buffer, so they need not be `Clone` or `Ord`.
+/// Items which compare equal are still treated as distinct.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, permutations};
+/// let mut perms = permutations(vec!['a', 'b', 'c']);
+/// assert_eq!(perms.size_hint(), (6, Some(6)));
+/// assert_eq!(perms.next(), Some(&['a', 'b', 'c'][..]));
+/// assert_eq!(perms.next(), Some(&['a', 'c', 'b'][..]));
+/// assert_eq!(perms.next(), Some(&['b', 'a', 'c'][..]));
+/// ```
+#[inline]
+pub fn permutations<T, I>(items: I) -> Permutations<T>
+where
+    I: Into<Vec<T>>,
+{
+    let items = items.into();
+    let n = items.len();
+    Permutations {
+        items,
+        front: (0..n).collect(),
+        back: (0..n).rev().collect(),
+        cur: vec![0; n],
+        pos: vec![0; n],
+        front_started: false,
+        back_started: false,
+        attached: None,
+        valid: false,
+        done: false,
+        yielded: 0,
+        total: factorial(n),
+    }
+}
+
+/// A streaming iterator over the permutations of a set of items.
+///
+/// Requires the `alloc` feature.
+#[derive(Clone, Debug)]
+pub struct Permutations<T> {
+    items: Vec<T>,
+    front: Vec<usize>,
+    back: Vec<usize>,
+    // scratch space used to rearrange `items` when switching between ends
+    cur: Vec<usize>,
+    pos: Vec<usize>,
+    front_started: bool,
+    back_started: bool,
+    // the end whose permutation `items` is currently arranged in
+    attached: Option<End>,
+    valid: bool,
+    done: bool,
+    yielded: usize,
+    total: Option<usize>,
+}
+
+impl<T> Permutations<T> {
+    fn advance_end(&mut self, end: End) {
+        self.valid = false;
+        if self.done {
+            return;
+        }
+
+        let mirror = self.attached == Some(end);
+        let (perm, other, started, other_started) = match end {
+            End::Front => (
+                &mut self.front,
+                &self.back,
+                &mut self.front_started,
+                self.back_started,
+            ),
+            End::Back => (
+                &mut self.back,
+                &self.front,
+                &mut self.back_started,
+                self.front_started,
+            ),
+        };
+
+        let moved = if *started {
+            step_permutation(perm, &mut self.items, mirror, end == End::Front)
+        } else {
+            *started = true;
+            true
+        };
+        if !moved || (other_started && perm == other) {
+            self.done = true;
+            return;
+        }
+
+        if !mirror {
+            match self.attached {
+                Some(End::Front) => self.cur.copy_from_slice(&self.front),
+                Some(End::Back) => self.cur.copy_from_slice(&self.back),
+                None => {
+                    for (i, c) in self.cur.iter_mut().enumerate() {
+                        *c = i;
+                    }
+                }
+            }
+            let target = match end {
+                End::Front => &self.front,
+                End::Back => &self.back,
+            };
+            rearrange(&mut self.items, &mut self.cur, &mut self.pos, target);
+            self.attached = Some(end);
+        }
+
+        self.yielded += 1;
+        self.valid = true;
+    }
+}
+
+impl<T> StreamingIterator for Permutations<T> {
+    type Item = [T];
+
+    #[inline]
+    fn advance(&mut self) {
+        self.advance_end(End::Front);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[T]> {
+        if self.valid {
+            Some(&self.items)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        match self.total {
+            Some(total) => (total - self.yielded, Some(total - self.yielded)),
+            None => (usize::MAX, None),
+        }
+    }
+}
+
+impl<T> DoubleEndedStreamingIterator for Permutations<T> {
+    #[inline]
+    fn advance_back(&mut self) {
+        self.advance_end(End::Back);
+    }
+}
+
+/// Steps `c` to the lexicographically next selection of `c.len()` indices below `n`, returning
+/// the first position which changed.
+fn next_selection(c: &mut [usize], n: usize, replacement: bool) -> Option<usize> {
+    let k = c.len();
+    let mut i = k;
+    loop {
+        if i == 0 {
+            return None;
+        }
+        i -= 1;
+        let max = if replacement { n - 1 } else { n - k + i };
+        if c[i] < max {
+            break;
+        }
+    }
+
+    c[i] += 1;
+    for j in i + 1..k {
+        c[j] = if replacement { c[i] } else { c[j - 1] + 1 };
+    }
+    Some(i)
+}
+
+/// Steps `c` to the lexicographically previous selection of `c.len()` indices below `n`,
+/// returning the first position which changed.
+fn prev_selection(c: &mut [usize], n: usize, replacement: bool) -> Option<usize> {
+    let k = c.len();
+    let mut i = k;
+    loop {
+        if i == 0 {
+            return None;
+        }
+        i -= 1;
+        let min = match (i, replacement) {
+            (0, _) => 0,
+            (_, true) => c[i - 1],
+            (_, false) => c[i - 1] + 1,
+        };
+        if c[i] > min {
+            break;
+        }
+    }
+
+    c[i] -= 1;
+    for (j, c) in c.iter_mut().enumerate().skip(i + 1) {
+        *c = if replacement { n - 1 } else { n - k + j };
+    }
+    Some(i)
+}
+
+/// The shared state of the combination iterators: the current selection of indices at each end.
+#[derive(Clone, Debug)]
+struct Selection {
+    n: usize,
+    replacement: bool,
+    front: Vec<usize>,
+    back: Vec<usize>,
+    front_started: bool,
+    back_started: bool,
+    done: bool,
+    yielded: usize,
+    total: Option<usize>,
+}
+
+impl Selection {
+    fn new(n: usize, k: usize, replacement: bool) -> Selection {
+        let (total, front, back) = if replacement {
+            let total = match (n, k) {
+                (_, 0) => Some(1),
+                (0, _) => Some(0),
+                _ => n.checked_add(k - 1).and_then(|m| binomial(m, k)),
+            };
+            (total, vec![0; k], vec![n.saturating_sub(1); k])
+        } else {
+            let back = (0..k).map(|i| (n + i).saturating_sub(k)).collect();
+            (binomial(n, k), (0..k).collect(), back)
+        };
+
+        Selection {
+            n,
+            replacement,
+            front,
+            back,
+            front_started: false,
+            back_started: false,
+            done: total == Some(0),
+            yielded: 0,
+            total,
+        }
+    }
+
+    /// Moves one end of the selection, returning the first position which changed, or `None` once
+    /// the ends have met.
+    fn advance(&mut self, end: End) -> Option<usize> {
+        if self.done {
+            return None;
+        }
+
+        let (c, other, started, other_started) = match end {
+            End::Front => (
+                &mut self.front,
+                &self.back,
+                &mut self.front_started,
+                self.back_started,
+            ),
+            End::Back => (
+                &mut self.back,
+                &self.front,
+                &mut self.back_started,
+                self.front_started,
+            ),
+        };
+
+        let changed = if !*started {
+            *started = true;
+            Some(0)
+        } else if end == End::Front {
+            next_selection(c, self.n, self.replacement)
+        } else {
+            prev_selection(c, self.n, self.replacement)
+        };
+
+        match changed {
+            Some(i) if !(other_started && c == other) => {
+                self.yielded += 1;
+                Some(i)
+            }
+            _ => {
+                self.done = true;
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self, end: End) -> &[usize] {
+        match end {
+            End::Front => &self.front,
+            End::Back => &self.back,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        match self.total {
+            Some(total) => (total - self.yielded, Some(total - self.yielded)),
+            None => (usize::MAX, None),
+        }
+    }
+}
+
+/// Creates a streaming iterator over all `k`-element combinations of the indices `0..n`, in
+/// lexicographic order.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, combinations};
+/// let mut combs = combinations(4, 2);
+/// assert_eq!(combs.size_hint(), (6, Some(6)));
+/// assert_eq!(combs.next(), Some(&[0, 1][..]));
+/// assert_eq!(combs.next(), Some(&[0, 2][..]));
+/// assert_eq!(combs.next(), Some(&[0, 3][..]));
+/// assert_eq!(combs.next(), Some(&[1, 2][..]));
+/// ```
+#[inline]
+pub fn combinations(n: usize, k: usize) -> Combinations {
+    Combinations {
+        selection: Selection::new(n, k, false),
+        current: None,
+    }
+}
+
+/// A streaming iterator over the combinations of a range of indices.
+///
+/// Requires the `alloc` feature.
+#[derive(Clone, Debug)]
+pub struct Combinations {
+    selection: Selection,
+    current: Option<End>,
+}
+
+impl StreamingIterator for Combinations {
+    type Item = [usize];
+
+    #[inline]
+    fn advance(&mut self) {
+        self.current = self.selection.advance(End::Front).map(|_| End::Front);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[usize]> {
+        self.current.map(|end| self.selection.get(end))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.selection.size_hint()
+    }
+}
+
+impl DoubleEndedStreamingIterator for Combinations {
+    #[inline]
+    fn advance_back(&mut self) {
+        self.current = self.selection.advance(End::Back).map(|_| End::Back);
+    }
+}
+
+/// Creates a streaming iterator over all `k`-element combinations of `items`, in lexicographic
+/// order of their positions.
+///
+/// Each combination is cloned into a single reused buffer; only the items which differ from the
+/// previous combination are cloned on each step.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, combinations_of};
+/// let mut combs = combinations_of(&["a", "b", "c"][..], 2);
+/// assert_eq!(combs.next(), Some(&["a", "b"][..]));
+/// assert_eq!(combs.next(), Some(&["a", "c"][..]));
+/// assert_eq!(combs.next(), Some(&["b", "c"][..]));
+/// assert_eq!(combs.next(), None);
+/// ```
+#[inline]
+pub fn combinations_of<T, I>(items: I, k: usize) -> CombinationsOf<T>
+where
+    T: Clone,
+    I: Into<Vec<T>>,
+{
+    let items = items.into();
+    CombinationsOf {
+        selection: Selection::new(items.len(), k, false),
+        items,
+        buf: Vec::with_capacity(k),
+        attached: None,
+        valid: false,
+    }
+}
+
+/// Creates a streaming iterator over all `k`-element combinations of `items` in which each item
+/// may be chosen more than once, in lexicographic order of their positions.
+///
+/// Each combination is cloned into a single reused buffer; only the items which differ from the
+/// previous combination are cloned on each step.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, combinations_with_replacement};
+/// let mut combs = combinations_with_replacement(vec![1, 2], 2);
+/// assert_eq!(combs.next(), Some(&[1, 1][..]));
+/// assert_eq!(combs.next(), Some(&[1, 2][..]));
+/// assert_eq!(combs.next(), Some(&[2, 2][..]));
+/// assert_eq!(combs.next(), None);
+/// ```
+#[inline]
+pub fn combinations_with_replacement<T, I>(items: I, k: usize) -> CombinationsOf<T>
+where
+    T: Clone,
+    I: Into<Vec<T>>,
+{
+    let items = items.into();
+    CombinationsOf {
+        selection: Selection::new(items.len(), k, true),
+        items,
+        buf: Vec::with_capacity(k),
+        attached: None,
+        valid: false,
+    }
+}
+
+/// A streaming iterator over the combinations of a set of items, returned by `combinations_of`
+/// and `combinations_with_replacement`.
+///
+/// Requires the `alloc` feature.
+#[derive(Clone, Debug)]
+pub struct CombinationsOf<T> {
+    items: Vec<T>,
+    selection: Selection,
+    buf: Vec<T>,
+    // the end whose selection `buf` currently holds
+    attached: Option<End>,
+    valid: bool,
+}
+
+impl<T> CombinationsOf<T>
+where
+    T: Clone,
+{
+    fn advance_end(&mut self, end: End) {
+        let changed = match self.selection.advance(end) {
+            Some(changed) => changed,
+            None => {
+                self.valid = false;
+                return;
+            }
+        };
+
+        let indices = self.selection.get(end);
+        if self.buf.len() < indices.len() {
+            let items = &self.items;
+            self.buf.clear();
+            self.buf.extend(indices.iter().map(|&i| items[i].clone()));
+        } else {
+            let start = if self.attached == Some(end) {
+                changed
+            } else {
+                0
+            };
+            for (slot, &i) in self.buf[start..].iter_mut().zip(&indices[start..]) {
+                slot.clone_from(&self.items[i]);
+            }
+        }
+        self.attached = Some(end);
+        self.valid = true;
+    }
+}
+
+impl<T> StreamingIterator for CombinationsOf<T>
+where
+    T: Clone,
+{
+    type Item = [T];
+
+    #[inline]
+    fn advance(&mut self) {
+        self.advance_end(End::Front);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[T]> {
+        if self.valid {
+            Some(&self.buf)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.selection.size_hint()
+    }
+}
+
+impl<T> DoubleEndedStreamingIterator for CombinationsOf<T>
+where
+    T: Clone,
+{
+    #[inline]
+    fn advance_back(&mut self) {
+        self.advance_end(End::Back);
+    }
+}
+
+/// Adds `steps` to the mixed-radix number `digits`, returning the first digit which changed, or
+/// `None` if the result does not fit.
+fn add_digits(digits: &mut [usize], shape: &[usize], steps: u128) -> Option<usize> {
+    let mut carry = steps;
+    let mut i = digits.len();
+    while carry > 0 {
+        if i == 0 {
+            return None;
+        }
+        i -= 1;
+        let radix = shape[i] as u128;
+        let sum = digits[i] as u128 + carry;
+        digits[i] = (sum % radix) as usize;
+        carry = sum / radix;
+    }
+    Some(i)
+}
+
+/// Subtracts `steps` from the mixed-radix number `digits`, returning the first digit which
+/// changed, or `None` if the result would be negative.
+fn sub_digits(digits: &mut [usize], shape: &[usize], steps: u128) -> Option<usize> {
+    let mut borrow = steps;
+    let mut i = digits.len();
+    while borrow > 0 {
+        if i == 0 {
+            return None;
+        }
+        i -= 1;
+        let radix = shape[i] as u128;
+        let digit = digits[i] as u128;
+        let take = borrow % radix;
+        borrow /= radix;
+        digits[i] = if digit >= take {
+            (digit - take) as usize
+        } else {
+            borrow += 1;
+            (digit + radix - take) as usize
+        };
+    }
+    Some(i)
+}
+
+/// The shared state of the product iterators: the current index at each end.
+#[derive(Clone, Debug)]
+struct Odometer {
+    shape: Vec<usize>,
+    front: Vec<usize>,
+    back: Vec<usize>,
+    front_started: bool,
+    back_started: bool,
+    done: bool,
+    consumed: usize,
+    total: Option<usize>,
+}
+
+impl Odometer {
+    fn new(shape: Vec<usize>) -> Odometer {
+        let total = shape.iter().try_fold(1usize, |acc, &d| acc.checked_mul(d));
+        Odometer {
+            front: vec![0; shape.len()],
+            back: shape.iter().map(|&d| d.saturating_sub(1)).collect(),
+            front_started: false,
+            back_started: false,
+            done: shape.contains(&0),
+            consumed: 0,
+            total,
+            shape,
+        }
+    }
+
+    /// Skips `n` indices and moves one end to the next one, returning the first digit which
+    /// changed, or `None` once the ends have met.
+    fn advance(&mut self, end: End, n: usize) -> Option<usize> {
+        if self.done {
+            return None;
+        }
+
+        let (digits, started) = match end {
+            End::Front => (&mut self.front, &mut self.front_started),
+            End::Back => (&mut self.back, &mut self.back_started),
+        };
+        let steps = if *started { n as u128 + 1 } else { n as u128 };
+        *started = true;
+
+        let changed = match end {
+            End::Front => add_digits(digits, &self.shape, steps),
+            End::Back => sub_digits(digits, &self.shape, steps),
+        };
+        let crossed = match end {
+            End::Front => self.back_started && self.front >= self.back,
+            End::Back => self.front_started && self.back <= self.front,
+        };
+
+        match changed {
+            Some(i) if !crossed => {
+                self.consumed = self.consumed.saturating_add(n).saturating_add(1);
+                Some(i)
+            }
+            _ => {
+                self.done = true;
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self, end: End) -> &[usize] {
+        match end {
+            End::Front => &self.front,
+            End::Back => &self.back,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        match self.total {
+            Some(total) => (total - self.consumed, Some(total - self.consumed)),
+            None => (usize::MAX, None),
+        }
+    }
+}
+
+/// Creates a streaming iterator over every index of an array of the given `shape`, in row-major
+/// order.
+///
+/// The index is kept in a single buffer which is updated in place, like an odometer.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, multi_index};
+/// let mut index = multi_index(&[2, 3]);
+/// assert_eq!(index.size_hint(), (6, Some(6)));
+/// assert_eq!(index.next(), Some(&[0, 0][..]));
+/// assert_eq!(index.next(), Some(&[0, 1][..]));
+/// assert_eq!(index.nth(2), Some(&[1, 1][..]));
+/// assert_eq!(index.next(), Some(&[1, 2][..]));
+/// assert_eq!(index.next(), None);
+/// ```
+#[inline]
+pub fn multi_index(shape: &[usize]) -> MultiIndex {
+    MultiIndex {
+        odometer: Odometer::new(shape.to_vec()),
+        current: None,
+    }
+}
+
+/// A streaming iterator over the indices of a multi-dimensional array.
+///
+/// Requires the `alloc` feature.
+#[derive(Clone, Debug)]
+pub struct MultiIndex {
+    odometer: Odometer,
+    current: Option<End>,
+}
+
+impl StreamingIterator for MultiIndex {
+    type Item = [usize];
+
+    #[inline]
+    fn advance(&mut self) {
+        self.current = self.odometer.advance(End::Front, 0).map(|_| End::Front);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[usize]> {
+        self.current.map(|end| self.odometer.get(end))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.odometer.size_hint()
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<&[usize]> {
+        self.current = self.odometer.advance(End::Front, n).map(|_| End::Front);
+        Self::get(self)
+    }
+}
+
+impl DoubleEndedStreamingIterator for MultiIndex {
+    #[inline]
+    fn advance_back(&mut self) {
+        self.current = self.odometer.advance(End::Back, 0).map(|_| End::Back);
+    }
+}
+
+/// Creates a streaming iterator over the cartesian product of `sets`, in row-major order.
+///
+/// Each selection, with one item from every set, is cloned into a single reused buffer; only the
+/// items which differ from the previous selection are cloned on each step. The index of each item
+/// within its set is available from `CartesianProduct::indices`.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, cartesian_product};
+/// let sizes = ["S", "L"];
+/// let colors = ["red", "green", "blue"];
+/// let mut product = cartesian_product(vec![&sizes[..], &colors[..]]);
+/// assert_eq!(product.next(), Some(&["S", "red"][..]));
+/// assert_eq!(product.next(), Some(&["S", "green"][..]));
+/// assert_eq!(product.indices(), Some(&[0, 1][..]));
+/// assert_eq!(product.nth(1), Some(&["L", "red"][..]));
+/// ```
+#[inline]
+pub fn cartesian_product<'a, T, I>(sets: I) -> CartesianProduct<'a, T>
+where
+    T: Clone,
+    I: IntoIterator<Item = &'a [T]>,
+{
+    let sets = sets.into_iter().collect::<Vec<_>>();
+    CartesianProduct {
+        odometer: Odometer::new(sets.iter().map(|s| s.len()).collect()),
+        buf: Vec::with_capacity(sets.len()),
+        sets,
+        attached: None,
+        current: None,
+    }
+}
+
+/// A streaming iterator over the cartesian product of a list of slices.
+///
+/// Requires the `alloc` feature.
+#[derive(Clone, Debug)]
+pub struct CartesianProduct<'a, T: 'a> {
+    sets: Vec<&'a [T]>,
+    odometer: Odometer,
+    buf: Vec<T>,
+    // the end whose selection `buf` currently holds
+    attached: Option<End>,
+    current: Option<End>,
+}
+
+impl<'a, T> CartesianProduct<'a, T>
+where
+    T: Clone,
+{
+    /// Returns the index of each item of the current selection within its set.
+    #[inline]
+    pub fn indices(&self) -> Option<&[usize]> {
+        self.current.map(|end| self.odometer.get(end))
+    }
+
+    fn advance_end(&mut self, end: End, n: usize) {
+        let changed = match self.odometer.advance(end, n) {
+            Some(changed) => changed,
+            None => {
+                self.current = None;
+                return;
+            }
+        };
+
+        let indices = self.odometer.get(end);
+        if self.buf.len() < indices.len() {
+            let sets = &self.sets;
+            self.buf.clear();
+            self.buf
+                .extend(indices.iter().zip(sets).map(|(&i, set)| set[i].clone()));
+        } else {
+            let start = if self.attached == Some(end) {
+                changed
+            } else {
+                0
+            };
+            for ((slot, &i), set) in self.buf[start..]
+                .iter_mut()
+                .zip(&indices[start..])
+                .zip(&self.sets[start..])
+            {
+                slot.clone_from(&set[i]);
+            }
+        }
+        self.attached = Some(end);
+        self.current = Some(end);
+    }
+}
+
+impl<'a, T> StreamingIterator for CartesianProduct<'a, T>
+where
+    T: Clone,
+{
+    type Item = [T];
+
+    #[inline]
+    fn advance(&mut self) {
+        self.advance_end(End::Front, 0);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[T]> {
+        match self.current {
+            Some(_) => Some(&self.buf),
+            None => None,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.odometer.size_hint()
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<&[T]> {
+        self.advance_end(End::Front, n);
+        Self::get(self)
+    }
+}
+
+impl<'a, T> DoubleEndedStreamingIterator for CartesianProduct<'a, T>
+where
+    T: Clone,
+{
+    #[inline]
+    fn advance_back(&mut self) {
+        self.advance_end(End::Back, 0);
+    }
+}
+
+/// Returns `2^n`, or `None` if it overflows.
+fn power_of_two(n: usize) -> Option<usize> {
+    if n < mem::size_of::<usize>() * 8 {
+        Some(1 << n)
+    } else {
+        None
+    }
+}
+
+/// Creates a streaming iterator over every subset of `items`, ordered by size and then
+/// lexicographically by the positions of the items chosen.
+///
+/// Each subset is cloned into a single reused buffer; only the items which differ from the
+/// previous subset are cloned on each step.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, powerset};
+/// let mut subsets = powerset(vec![1, 2, 3]);
+/// assert_eq!(subsets.size_hint(), (8, Some(8)));
+/// assert_eq!(subsets.next(), Some(&[][..]));
+/// assert_eq!(subsets.next(), Some(&[1][..]));
+/// assert_eq!(subsets.next(), Some(&[2][..]));
+/// assert_eq!(subsets.next(), Some(&[3][..]));
+/// assert_eq!(subsets.next(), Some(&[1, 2][..]));
+/// ```
+#[inline]
+pub fn powerset<T, I>(items: I) -> Powerset<T>
+where
+    T: Clone,
+    I: Into<Vec<T>>,
+{
+    let items = items.into();
+    Powerset {
+        selection: Selection::new(items.len(), 0, false),
+        total: power_of_two(items.len()),
+        buf: Vec::with_capacity(items.len()),
+        items,
+        valid: false,
+        yielded: 0,
+    }
+}
+
+/// A streaming iterator over the subsets of a set of items.
+///
+/// Requires the `alloc` feature.
+#[derive(Clone, Debug)]
+pub struct Powerset<T> {
+    items: Vec<T>,
+    selection: Selection,
+    buf: Vec<T>,
+    valid: bool,
+    yielded: usize,
+    total: Option<usize>,
+}
+
+impl<T> StreamingIterator for Powerset<T>
+where
+    T: Clone,
+{
+    type Item = [T];
+
+    #[inline]
+    fn advance(&mut self) {
+        loop {
+            if let Some(changed) = self.selection.advance(End::Front) {
+                let indices = self.selection.get(End::Front);
+                if self.buf.len() < indices.len() {
+                    let items = &self.items;
+                    self.buf.clear();
+                    self.buf.extend(indices.iter().map(|&i| items[i].clone()));
+                } else {
+                    for (slot, &i) in self.buf[changed..].iter_mut().zip(&indices[changed..]) {
+                        slot.clone_from(&self.items[i]);
+                    }
+                }
+                self.yielded += 1;
+                self.valid = true;
+                return;
+            }
+
+            let k = self.selection.get(End::Front).len();
+            if k == self.items.len() {
+                self.valid = false;
+                return;
+            }
+            self.selection = Selection::new(self.items.len(), k + 1, false);
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[T]> {
+        if self.valid {
+            Some(&self.buf)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.total {
+            Some(total) => (total - self.yielded, Some(total - self.yielded)),
+            None => (usize::MAX, None),
+        }
+    }
+}
+
+/// Creates a streaming iterator over every subset of the indices `0..n`, in an order in which
+/// consecutive subsets differ by exactly one index.
+///
+/// Subsets are yielded as a slice of `n` flags, indicating whether each index is a member. The
+/// first subset is empty, and `GrayCodeSubsets::toggled` returns the index added or removed by
+/// each subsequent step, so that consumers can update their state incrementally.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, gray_code_subsets};
+/// let weights = [3, 5, 7];
+/// let mut subsets = gray_code_subsets(weights.len());
+/// let mut total = 0;
+/// loop {
+///     subsets.advance();
+///     let members = match subsets.get() {
+///         Some(members) => members,
+///         None => break,
+///     };
+///     if let Some(i) = subsets.toggled() {
+///         if members[i] {
+///             total += weights[i];
+///         } else {
+///             total -= weights[i];
+///         }
+///     }
+///     let expected: i32 = (0..3).filter(|&i| members[i]).map(|i| weights[i]).sum();
+///     assert_eq!(total, expected);
+/// }
+/// ```
+#[inline]
+pub fn gray_code_subsets(n: usize) -> GrayCodeSubsets {
+    GrayCodeSubsets {
+        members: vec![false; n],
+        step: 0,
+        total: power_of_two(n),
+        toggled: None,
+        valid: false,
+        done: false,
+    }
+}
+
+/// A streaming iterator over subsets in Gray code order.
+///
+/// Requires the `alloc` feature.
+#[derive(Clone, Debug)]
+pub struct GrayCodeSubsets {
+    members: Vec<bool>,
+    // the number of subsets yielded so far
+    step: usize,
+    total: Option<usize>,
+    toggled: Option<usize>,
+    valid: bool,
+    done: bool,
+}
+
+impl GrayCodeSubsets {
+    /// Returns the index added to or removed from the subset by the last call to `advance`.
+    ///
+    /// Returns `None` before iteration has started, for the initial empty subset, and once
+    /// iteration has finished.
+    #[inline]
+    pub fn toggled(&self) -> Option<usize> {
+        self.toggled
+    }
+}
+
+impl StreamingIterator for GrayCodeSubsets {
+    type Item = [bool];
+
+    #[inline]
+    fn advance(&mut self) {
+        self.toggled = None;
+        if self.done || Some(self.step) == self.total {
+            self.done = true;
+            self.valid = false;
+            return;
+        }
+
+        if self.step != 0 {
+            // the k-th step flips the bit which changes when counting from k - 1 to k
+            let i = self.step.trailing_zeros() as usize;
+            self.members[i] = !self.members[i];
+            self.toggled = Some(i);
+        }
+        match self.step.checked_add(1) {
+            Some(step) => self.step = step,
+            None => self.done = true,
+        }
+        self.valid = true;
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[bool]> {
+        if self.valid {
+            Some(&self.members)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        match self.total {
+            Some(total) => (total - self.step, Some(total - self.step)),
+            None => (usize::MAX, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::borrow::ToOwned;
+    use core::fmt::Debug;
+
+    use super::*;
+
+    fn collect<I, T>(mut it: I) -> Vec<Vec<T>>
+    where
+        I: StreamingIterator<Item = [T]>,
+        T: Clone,
+    {
+        let mut items = vec![];
+        let len = it.size_hint().0;
+        while let Some(item) = it.next() {
+            items.push(item.to_vec());
+        }
+        assert_eq!(items.len(), len);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        items
+    }
+
+    fn check_back<I, T>(it: I)
+    where
+        I: DoubleEndedStreamingIterator<Item = [T]> + Clone,
+        T: Clone + PartialEq + Debug,
+    {
+        let mut forward = collect(it.clone());
+        forward.reverse();
+        assert_eq!(collect(it.clone().rev()), forward);
+
+        // alternate between ends until they meet in the middle
+        let mut it = it;
+        let mut front = vec![];
+        let mut back = vec![];
+        while let Some(item) = it.next() {
+            front.push(item.to_vec());
+            match it.next_back() {
+                Some(item) => back.push(item.to_vec()),
+                None => break,
+            }
+        }
+        assert!(it.next().is_none());
+        back.reverse();
+        front.extend(back);
+        forward.reverse();
+        assert_eq!(front, forward);
+    }
+
+    #[test]
+    fn permutations_order() {
+        let perms = collect(permutations(vec![1, 2, 3]));
+        assert_eq!(
+            perms,
+            [
+                [1, 2, 3],
+                [1, 3, 2],
+                [2, 1, 3],
+                [2, 3, 1],
+                [3, 1, 2],
+                [3, 2, 1],
+            ]
+        );
+        assert_eq!(collect(permutations(Vec::<u8>::new())), [[]]);
+
+        for n in 0..6 {
+            check_back(permutations((0..n).collect::<Vec<_>>()));
+        }
+    }
+
+    #[test]
+    fn permutations_not_clone() {
+        #[derive(Debug, PartialEq)]
+        struct NotClone(u8);
+
+        let mut perms = permutations(vec![NotClone(0), NotClone(1)]);
+        assert_eq!(perms.next(), Some(&[NotClone(0), NotClone(1)][..]));
+        assert_eq!(perms.next_back(), Some(&[NotClone(1), NotClone(0)][..]));
+        assert_eq!(perms.next(), None);
+    }
+
+    #[test]
+    fn permutations_overflow() {
+        let perms = permutations((0..21).collect::<Vec<_>>());
+        assert_eq!(perms.size_hint(), (usize::MAX, None));
+        let perms = permutations((0..20).collect::<Vec<u64>>());
+        assert_eq!(perms.size_hint().1, Some(2_432_902_008_176_640_000));
+    }
+
+    #[test]
+    fn combinations_order() {
+        assert_eq!(
+            collect(combinations(4, 2)),
+            [[0, 1], [0, 2], [0, 3], [1, 2], [1, 3], [2, 3]]
+        );
+        assert_eq!(collect(combinations(3, 0)), [[]]);
+        assert!(collect(combinations(2, 3)).is_empty());
+
+        for n in 0..6 {
+            for k in 0..n + 2 {
+                check_back(combinations(n, k));
+                check_back(combinations_of((0..n).collect::<Vec<_>>(), k));
+                check_back(combinations_with_replacement((0..n).collect::<Vec<_>>(), k));
+            }
+        }
+    }
+
+    #[test]
+    fn combinations_with_replacement_order() {
+        assert_eq!(
+            collect(combinations_with_replacement(&["a", "b", "c"][..], 2)),
+            [
+                ["a", "a"],
+                ["a", "b"],
+                ["a", "c"],
+                ["b", "b"],
+                ["b", "c"],
+                ["c", "c"],
+            ]
+        );
+        assert_eq!(
+            collect(combinations_with_replacement(Vec::<u8>::new(), 0)),
+            [[]]
+        );
+        assert!(collect(combinations_with_replacement(Vec::<u8>::new(), 1)).is_empty());
+    }
+
+    #[test]
+    fn combinations_overflow() {
+        assert_eq!(combinations(200, 100).size_hint(), (usize::MAX, None));
+        assert_eq!(
+            combinations(64, 32).size_hint().1,
+            Some(1_832_624_140_942_590_534)
+        );
+        assert_eq!(
+            combinations_with_replacement(vec![0; 1000], 1000).size_hint(),
+            (usize::MAX, None)
+        );
+    }
+
+    #[test]
+    fn multi_index_order() {
+        assert_eq!(
+            collect(multi_index(&[2, 2, 3])),
+            [
+                [0, 0, 0],
+                [0, 0, 1],
+                [0, 0, 2],
+                [0, 1, 0],
+                [0, 1, 1],
+                [0, 1, 2],
+                [1, 0, 0],
+                [1, 0, 1],
+                [1, 0, 2],
+                [1, 1, 0],
+                [1, 1, 1],
+                [1, 1, 2],
+            ]
+        );
+        assert_eq!(collect(multi_index(&[])), [[]]);
+        assert!(collect(multi_index(&[3, 0, 2])).is_empty());
+
+        for shape in &[&[][..], &[1], &[4], &[3, 2], &[2, 1, 3], &[2, 0]] {
+            check_back(multi_index(shape));
+            let sets = shape
+                .iter()
+                .map(|&n| (0..n).collect())
+                .collect::<Vec<Vec<_>>>();
+            check_back(cartesian_product(sets.iter().map(|s| &s[..])));
+        }
+    }
+
+    #[test]
+    fn multi_index_nth() {
+        let mut it = multi_index(&[3, 4]);
+        assert_eq!(it.nth(5), Some(&[1, 1][..]));
+        assert_eq!(it.size_hint(), (6, Some(6)));
+        assert_eq!(it.next_back(), Some(&[2, 3][..]));
+        assert_eq!(it.nth(3), Some(&[2, 1][..]));
+        assert_eq!(it.size_hint(), (1, Some(1)));
+        assert_eq!(it.nth(1), None);
+        assert_eq!(it.next(), None);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+
+        let mut it = multi_index(&[usize::MAX, usize::MAX, 2]);
+        assert_eq!(it.size_hint(), (usize::MAX, None));
+        assert_eq!(it.nth(usize::MAX), Some(&[0, usize::MAX / 2, 1][..]));
+        assert_eq!(
+            it.rev().next(),
+            Some(&[usize::MAX - 1, usize::MAX - 1, 1][..])
+        );
+    }
+
+    #[test]
+    fn cartesian_product_selection() {
+        let a = ["a".to_owned(), "b".to_owned()];
+        let b = ["x".to_owned(), "y".to_owned(), "z".to_owned()];
+        let mut it = cartesian_product(vec![&a[..], &b[..]]);
+        assert_eq!(it.size_hint(), (6, Some(6)));
+        assert_eq!(it.next(), Some(&["a".to_owned(), "x".to_owned()][..]));
+        assert_eq!(it.nth(2), Some(&["b".to_owned(), "x".to_owned()][..]));
+        assert_eq!(it.indices(), Some(&[1, 0][..]));
+        assert_eq!(it.next_back(), Some(&["b".to_owned(), "z".to_owned()][..]));
+        assert_eq!(it.next(), Some(&["b".to_owned(), "y".to_owned()][..]));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.indices(), None);
+    }
+
+    #[test]
+    fn powerset_order() {
+        assert_eq!(
+            collect(powerset(&["a", "b", "c"][..])),
+            [
+                &[][..],
+                &["a"],
+                &["b"],
+                &["c"],
+                &["a", "b"],
+                &["a", "c"],
+                &["b", "c"],
+                &["a", "b", "c"],
+            ]
+        );
+        assert_eq!(collect(powerset(Vec::<u8>::new())), [[]]);
+        assert_eq!(collect(powerset((0..10).collect::<Vec<_>>())).len(), 1024);
+        assert_eq!(
+            powerset((0..64).collect::<Vec<_>>()).size_hint(),
+            (usize::MAX, None)
+        );
+    }
+
+    #[test]
+    fn gray_code() {
+        let mut it = gray_code_subsets(4);
+        let mut seen = vec![];
+        let mut prev = vec![false; 4];
+        assert_eq!(it.size_hint(), (16, Some(16)));
+        loop {
+            it.advance();
+            let members = match it.get() {
+                Some(members) => members,
+                None => break,
+            };
+            let diff = (0..4)
+                .filter(|&i| members[i] != prev[i])
+                .collect::<Vec<_>>();
+            match it.toggled() {
+                Some(i) => assert_eq!(diff, [i]),
+                None => assert!(diff.is_empty()),
+            }
+            prev = members.to_vec();
+            seen.push(prev.clone());
+        }
+        assert_eq!(it.toggled(), None);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 16);
+
+        assert_eq!(collect(gray_code_subsets(0)), [[]]);
+    }
+}
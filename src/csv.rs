@@ -0,0 +1,499 @@
+//! A streaming iterator over the records of delimited text such as CSV and TSV.
+
+use std::fmt;
+use std::io::{self, BufRead};
+use std::ops::Range;
+
+use super::StreamingIterator;
+
+/// The delimiter and quote characters used by `csv_records`.
+///
+/// Requires the `std` feature.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Dialect {
+    /// The character separating fields.
+    pub delimiter: char,
+    /// The character used to quote fields, if any.
+    ///
+    /// Inside a quoted field, delimiters and line breaks are part of the field and two quote
+    /// characters in a row stand for a single literal quote character.
+    pub quote: Option<char>,
+}
+
+impl Dialect {
+    /// Comma separated fields, quoted with `"`.
+    #[inline]
+    pub fn csv() -> Dialect {
+        Dialect {
+            delimiter: ',',
+            quote: Some('"'),
+        }
+    }
+
+    /// Tab separated fields, without quoting.
+    #[inline]
+    pub fn tsv() -> Dialect {
+        Dialect {
+            delimiter: '\t',
+            quote: None,
+        }
+    }
+}
+
+impl Default for Dialect {
+    #[inline]
+    fn default() -> Dialect {
+        Dialect::csv()
+    }
+}
+
+/// Creates a streaming iterator over the records of delimited text read from `reader`.
+///
+/// Every record is decoded into the same `Record`, so no allocation takes place once the buffers
+/// have grown to fit the largest record. Line breaks may be either `\n` or `\r\n`, and an empty
+/// line is a record with no fields. An I/O error, invalid UTF-8 or a quoted field left open at the
+/// end of the input ends iteration and is reported by `CsvRecords::error`.
+///
+/// Requires the `std` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, csv_records, Dialect};
+/// let data = "name,quote\nalice,\"hello, \"\"world\"\"\"\n";
+/// let mut records = csv_records(data.as_bytes(), Dialect::csv());
+/// while let Some(record) = records.next() {
+///     println!("{} says {}", record.field(0), record.field(1));
+/// }
+/// assert!(records.error().is_none());
+/// ```
+#[inline]
+pub fn csv_records<R>(reader: R, dialect: Dialect) -> CsvRecords<R>
+where
+    R: BufRead,
+{
+    CsvRecords {
+        reader,
+        dialect,
+        record: Record {
+            line: String::new(),
+            unescaped: String::new(),
+            fields: vec![],
+        },
+        valid: false,
+        done: false,
+        error: None,
+    }
+}
+
+/// A streaming iterator over the records of delimited text read from an `io::BufRead`.
+///
+/// Requires the `std` feature.
+#[derive(Debug)]
+pub struct CsvRecords<R> {
+    reader: R,
+    dialect: Dialect,
+    record: Record,
+    valid: bool,
+    done: bool,
+    error: Option<io::Error>,
+}
+
+impl<R> CsvRecords<R> {
+    /// Returns the error which ended iteration, if any.
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error which ended iteration, if any, leaving `None` in its place.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Consumes the iterator, returning the underlying reader.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> CsvRecords<R>
+where
+    R: BufRead,
+{
+    /// Decodes the next record, returning `false` at the end of the input.
+    fn read_record(&mut self) -> io::Result<bool> {
+        let Dialect { delimiter, quote } = self.dialect;
+        let record = &mut self.record;
+        record.line.clear();
+        record.unescaped.clear();
+        record.fields.clear();
+        if self.reader.read_line(&mut record.line)? == 0 {
+            return Ok(false);
+        }
+        if record.line == "\n" || record.line == "\r\n" {
+            return Ok(true);
+        }
+
+        let mut pos = 0;
+        loop {
+            let (field, end) = match quote {
+                Some(quote) if record.line[pos..].starts_with(quote) => read_quoted(
+                    &mut self.reader,
+                    record,
+                    pos + quote.len_utf8(),
+                    quote,
+                    delimiter,
+                )?,
+                _ => {
+                    let end = find_delimiter(&record.line, pos, delimiter);
+                    let field = Field {
+                        range: pos..end,
+                        unescaped: false,
+                    };
+                    (field, end)
+                }
+            };
+            record.fields.push(field);
+            if end == content_end(&record.line) {
+                break;
+            }
+            pos = end + delimiter.len_utf8();
+        }
+
+        Ok(true)
+    }
+}
+
+/// Reads a quoted field whose contents begin at `start`, returning it and the position following
+/// it.
+///
+/// The field's range is into the line unless it contains escaped quotes or text after its closing
+/// quote, in which case its contents are copied into the record's unescaped buffer.
+fn read_quoted<R>(
+    reader: &mut R,
+    record: &mut Record,
+    start: usize,
+    quote: char,
+    delimiter: char,
+) -> io::Result<(Field, usize)>
+where
+    R: BufRead,
+{
+    let mut scan = start;
+    let mut escaped = false;
+    let close = loop {
+        match record.line[scan..].find(quote) {
+            Some(i) => {
+                let after = scan + i + quote.len_utf8();
+                if record.line[after..].starts_with(quote) {
+                    escaped = true;
+                    scan = after + quote.len_utf8();
+                } else {
+                    break scan + i;
+                }
+            }
+            None => {
+                // a quoted field continues onto the next line
+                scan = record.line.len();
+                if reader.read_line(&mut record.line)? == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unterminated quoted field",
+                    ));
+                }
+            }
+        }
+    };
+
+    let after = close + quote.len_utf8();
+    let end = find_delimiter(&record.line, after, delimiter);
+    if !escaped && end == after {
+        let field = Field {
+            range: start..close,
+            unescaped: false,
+        };
+        return Ok((field, end));
+    }
+
+    let unescaped_start = record.unescaped.len();
+    let mut chars = record.line[start..close].chars();
+    while let Some(c) = chars.next() {
+        record.unescaped.push(c);
+        if c == quote {
+            chars.next();
+        }
+    }
+    record.unescaped.push_str(&record.line[after..end]);
+    let field = Field {
+        range: unescaped_start..record.unescaped.len(),
+        unescaped: true,
+    };
+    Ok((field, end))
+}
+
+/// Returns the position of the first delimiter at or after `pos`, or the end of the line's content.
+fn find_delimiter(line: &str, pos: usize, delimiter: char) -> usize {
+    let end = content_end(line);
+    match line[pos..end].find(delimiter) {
+        Some(i) => pos + i,
+        None => end,
+    }
+}
+
+/// Returns the length of `line` without its line break.
+fn content_end(line: &str) -> usize {
+    if line.ends_with("\r\n") {
+        line.len() - 2
+    } else if line.ends_with('\n') {
+        line.len() - 1
+    } else {
+        line.len()
+    }
+}
+
+impl<R> StreamingIterator for CsvRecords<R>
+where
+    R: BufRead,
+{
+    type Item = Record;
+
+    #[inline]
+    fn advance(&mut self) {
+        self.valid = false;
+        if self.done {
+            return;
+        }
+
+        match self.read_record() {
+            Ok(true) => self.valid = true,
+            Ok(false) => self.done = true,
+            Err(e) => {
+                self.done = true;
+                self.error = Some(e);
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&Record> {
+        if self.valid {
+            Some(&self.record)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Field {
+    range: Range<usize>,
+    // whether `range` is into `Record::unescaped` rather than `Record::line`
+    unescaped: bool,
+}
+
+/// A view of the fields of a record yielded by `CsvRecords`.
+///
+/// Fields are ranges of the line buffer the record was read into. Only quoted fields containing
+/// escaped quote characters are copied, with quoting removed, into a second buffer.
+///
+/// Requires the `std` feature.
+pub struct Record {
+    line: String,
+    unescaped: String,
+    fields: Vec<Field>,
+}
+
+impl Record {
+    /// Returns the number of fields in the record.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Determines if the record has no fields.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the field at index `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    #[inline]
+    pub fn field(&self, i: usize) -> &str {
+        let field = &self.fields[i];
+        if field.unescaped {
+            &self.unescaped[field.range.clone()]
+        } else {
+            &self.line[field.range.clone()]
+        }
+    }
+
+    /// Returns the field at index `i`, or `None` if it is out of bounds.
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<&str> {
+        if i < self.len() {
+            Some(self.field(i))
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the fields of the record.
+    #[inline]
+    pub fn iter(&self) -> RecordFields<'_> {
+        RecordFields {
+            record: self,
+            front: 0,
+            back: self.len(),
+        }
+    }
+}
+
+impl fmt::Debug for Record {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a Record {
+    type Item = &'a str;
+    type IntoIter = RecordFields<'a>;
+
+    #[inline]
+    fn into_iter(self) -> RecordFields<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the fields of a `Record`.
+///
+/// Requires the `std` feature.
+#[derive(Clone, Debug)]
+pub struct RecordFields<'a> {
+    record: &'a Record,
+    front: usize,
+    back: usize,
+}
+
+impl<'a> Iterator for RecordFields<'a> {
+    type Item = &'a str;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a str> {
+        if self.front == self.back {
+            return None;
+        }
+        let field = self.record.field(self.front);
+        self.front += 1;
+        Some(field)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for RecordFields<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a str> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.record.field(self.back))
+    }
+}
+
+impl<'a> ExactSizeIterator for RecordFields<'a> {}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::*;
+
+    fn collect<R: BufRead>(it: &mut CsvRecords<R>) -> Vec<Vec<String>> {
+        let mut records = vec![];
+        while let Some(record) = it.next() {
+            records.push(record.iter().map(str::to_owned).collect());
+        }
+        records
+    }
+
+    #[test]
+    fn simple() {
+        let data = "a,b,c\n1,,3\r\n\nlast";
+        let mut it = csv_records(data.as_bytes(), Dialect::csv());
+        assert_eq!(
+            collect(&mut it),
+            [
+                vec!["a", "b", "c"],
+                vec!["1", "", "3"],
+                vec![],
+                vec!["last"]
+            ]
+        );
+        assert!(it.error().is_none());
+    }
+
+    #[test]
+    fn quoting() {
+        let data =
+            "\"a,b\",\"say \"\"hi\"\"\",x\"y\n\"multi\nline\r\nfield\",end\n\"\"\n\"a\"b,\"\"\r\n";
+        let mut it = csv_records(data.as_bytes(), Dialect::csv());
+        assert_eq!(
+            collect(&mut it),
+            [
+                vec!["a,b", "say \"hi\"", "x\"y"],
+                vec!["multi\nline\r\nfield", "end"],
+                vec![""],
+                vec!["ab", ""],
+            ]
+        );
+        assert!(it.error().is_none());
+    }
+
+    #[test]
+    fn dialects() {
+        let data = "a\t'b\"c'\t'd''e'\n";
+        let mut it = csv_records(data.as_bytes(), Dialect::tsv());
+        assert_eq!(collect(&mut it), [["a", "'b\"c'", "'d''e'"]]);
+
+        let dialect = Dialect {
+            delimiter: ';',
+            quote: Some('\''),
+        };
+        let mut it = csv_records("a;'b;c';'d''e'".as_bytes(), dialect);
+        assert_eq!(collect(&mut it), [["a", "b;c", "d'e"]]);
+    }
+
+    #[test]
+    fn record() {
+        let mut it = csv_records("x,yz,\n".as_bytes(), Dialect::csv());
+        let record = it.next().unwrap();
+        assert_eq!(record.len(), 3);
+        assert_eq!(record.field(1), "yz");
+        assert_eq!(record.get(2), Some(""));
+        assert_eq!(record.get(3), None);
+        assert_eq!(record.iter().rev().collect::<Vec<_>>(), ["", "yz", "x"]);
+        assert_eq!(format!("{:?}", record), r#"["x", "yz", ""]"#);
+    }
+
+    #[test]
+    fn unterminated() {
+        let mut it = csv_records("a\n\"b,c\nd".as_bytes(), Dialect::csv());
+        assert_eq!(collect(&mut it), [["a"]]);
+        assert_eq!(
+            it.error().map(io::Error::kind),
+            Some(io::ErrorKind::InvalidData)
+        );
+        it.advance();
+        assert!(it.get().is_none());
+    }
+}
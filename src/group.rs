@@ -0,0 +1,660 @@
+//! Streaming iterators which group consecutive elements.
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::ToOwned;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::borrow::Borrow;
+use core::cmp;
+#[cfg(feature = "alloc")]
+use core::fmt;
+
+use super::StreamingIterator;
+
+/// Computes and compares the keys of elements for `GroupBy`.
+///
+/// This is implemented by closures returning a reference to the key of an element, as taken by
+/// `StreamingIterator::group_by`, and by `ByValue`, which wraps the closures returning a computed
+/// key taken by `StreamingIterator::group_by_key`.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub trait GroupKey<T: ?Sized, K: ?Sized + ToOwned> {
+    /// Determines if the key of `item` is equal to `key`.
+    fn matches(&mut self, item: &T, key: &K::Owned) -> bool;
+
+    /// Stores the key of `item` in `key`, reusing its allocation if there is one.
+    fn store(&mut self, item: &T, key: &mut Option<K::Owned>);
+}
+
+#[cfg(feature = "alloc")]
+impl<T, K, F> GroupKey<T, K> for F
+where
+    T: ?Sized,
+    K: ?Sized + ToOwned + PartialEq,
+    F: FnMut(&T) -> &K,
+{
+    #[inline]
+    fn matches(&mut self, item: &T, key: &K::Owned) -> bool {
+        self(item) == key.borrow()
+    }
+
+    #[inline]
+    fn store(&mut self, item: &T, key: &mut Option<K::Owned>) {
+        let new = self(item);
+        match *key {
+            Some(ref mut owned) => new.clone_into(owned),
+            None => *key = Some(new.to_owned()),
+        }
+    }
+}
+
+/// A closure returning the key of an element by value, as used by
+/// `StreamingIterator::group_by_key`.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct ByValue<F>(F);
+
+#[cfg(feature = "alloc")]
+impl<F> ByValue<F> {
+    #[inline]
+    pub(crate) fn new(f: F) -> ByValue<F> {
+        ByValue(f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, K, F> GroupKey<T, K> for ByValue<F>
+where
+    T: ?Sized,
+    K: Clone + PartialEq,
+    F: FnMut(&T) -> K,
+{
+    #[inline]
+    fn matches(&mut self, item: &T, key: &K) -> bool {
+        (self.0)(item) == *key
+    }
+
+    #[inline]
+    fn store(&mut self, item: &T, key: &mut Option<K>) {
+        *key = Some((self.0)(item));
+    }
+}
+
+/// A streaming iterator over groups of consecutive elements with equal keys.
+///
+/// The iterator itself yields the key of each group, and `group` or `next_group` return a `Group`
+/// over the elements of the current group. Advancing to the next group skips any elements of the
+/// current group which have not been consumed.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub struct GroupBy<I, K: ?Sized + ToOwned, F> {
+    it: I,
+    f: F,
+    key: Option<K::Owned>,
+    // whether the current element of `it` has been yielded by a `Group`
+    yielded: bool,
+    started: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<I, K, F> GroupBy<I, K, F>
+where
+    K: ?Sized + ToOwned,
+{
+    #[inline]
+    pub(crate) fn new(it: I, f: F) -> GroupBy<I, K, F> {
+        GroupBy {
+            it,
+            f,
+            key: None,
+            yielded: false,
+            started: false,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, K, F> GroupBy<I, K, F>
+where
+    I: StreamingIterator,
+    K: ?Sized + ToOwned,
+    F: GroupKey<I::Item, K>,
+{
+    /// Returns the current group, or `None` if the iterator has not been advanced or has reached
+    /// its end.
+    #[inline]
+    pub fn group(&mut self) -> Option<Group<'_, I, K, F>> {
+        if self.key.is_some() {
+            Some(Group {
+                parent: self,
+                valid: false,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Advances to the next group and returns it.
+    #[inline]
+    pub fn next_group(&mut self) -> Option<Group<'_, I, K, F>> {
+        self.advance();
+        self.group()
+    }
+
+    /// Determines if the current element of the underlying iterator belongs to the current group.
+    #[inline]
+    fn in_group(&mut self) -> bool {
+        match (self.it.get(), &self.key) {
+            (Some(item), Some(key)) => self.f.matches(item, key),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, K, F> StreamingIterator for GroupBy<I, K, F>
+where
+    I: StreamingIterator,
+    K: ?Sized + ToOwned,
+    F: GroupKey<I::Item, K>,
+{
+    type Item = K;
+
+    #[inline]
+    fn advance(&mut self) {
+        if !self.started {
+            self.started = true;
+            self.it.advance();
+        }
+
+        while self.yielded || self.in_group() {
+            self.yielded = false;
+            self.it.advance();
+        }
+
+        match self.it.get() {
+            Some(item) => self.f.store(item, &mut self.key),
+            None => self.key = None,
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&K> {
+        self.key.as_ref().map(Borrow::borrow)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.it.size_hint();
+        // once started, the remaining elements may all belong to the current group
+        let lower = if !self.started && lower > 0 { 1 } else { 0 };
+        (lower, upper)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, K, F> fmt::Debug for GroupBy<I, K, F>
+where
+    I: fmt::Debug,
+    K: ?Sized + ToOwned,
+    K::Owned: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("GroupBy")
+            .field("it", &self.it)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// A streaming iterator over the elements of a single group of a `GroupBy`.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub struct Group<'a, I: 'a, K: ?Sized + ToOwned + 'a, F: 'a> {
+    parent: &'a mut GroupBy<I, K, F>,
+    valid: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, I, K, F> Group<'a, I, K, F>
+where
+    K: ?Sized + ToOwned,
+{
+    /// Returns the key shared by the elements of the group.
+    #[inline]
+    pub fn key(&self) -> &K {
+        self.parent.key.as_ref().unwrap().borrow()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, I, K, F> StreamingIterator for Group<'a, I, K, F>
+where
+    I: StreamingIterator,
+    K: ?Sized + ToOwned,
+    F: GroupKey<I::Item, K>,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        if self.parent.yielded {
+            self.parent.yielded = false;
+            self.parent.it.advance();
+        }
+        self.valid = self.parent.in_group();
+        self.parent.yielded = self.valid;
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&I::Item> {
+        if self.valid {
+            self.parent.it.get()
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = self.parent.it.size_hint().1;
+        if self.parent.yielded {
+            (0, upper)
+        } else {
+            // the parent's current element may still belong to this group
+            (0, upper.and_then(|n| n.checked_add(1)))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, I, K, F> fmt::Debug for Group<'a, I, K, F>
+where
+    K: ?Sized + ToOwned + fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Group").field("key", &self.key()).finish()
+    }
+}
+
+/// A streaming iterator which merges runs of adjacent elements into a reused accumulator.
+#[derive(Debug)]
+pub struct Coalesce<I, A, N, M> {
+    it: I,
+    acc: A,
+    init: N,
+    merge: M,
+    started: bool,
+    valid: bool,
+}
+
+impl<I, A, N, M> Coalesce<I, A, N, M>
+where
+    A: Default,
+{
+    #[inline]
+    pub(crate) fn new(it: I, init: N, merge: M) -> Coalesce<I, A, N, M> {
+        Coalesce {
+            it,
+            acc: A::default(),
+            init,
+            merge,
+            started: false,
+            valid: false,
+        }
+    }
+}
+
+impl<I, A, N, M> StreamingIterator for Coalesce<I, A, N, M>
+where
+    I: StreamingIterator,
+    N: FnMut(&mut A, &I::Item),
+    M: FnMut(&mut A, &I::Item) -> bool,
+{
+    type Item = A;
+
+    #[inline]
+    fn advance(&mut self) {
+        if !self.started {
+            self.started = true;
+            self.it.advance();
+        }
+
+        // the underlying iterator is left on the first element of the next run
+        self.valid = match self.it.get() {
+            Some(item) => {
+                (self.init)(&mut self.acc, item);
+                true
+            }
+            None => false,
+        };
+        if self.valid {
+            loop {
+                self.it.advance();
+                match self.it.get() {
+                    Some(item) if (self.merge)(&mut self.acc, item) => {}
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&A> {
+        if self.valid {
+            Some(&self.acc)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        runs_hint(&self.it, self.started)
+    }
+}
+
+/// Returns the bounds on the number of runs remaining in an iterator left on the first element of
+/// the next run.
+#[inline]
+fn runs_hint<I>(it: &I, started: bool) -> (usize, Option<usize>)
+where
+    I: StreamingIterator,
+{
+    let (lower, upper) = it.size_hint();
+    if !started {
+        (cmp::min(lower, 1), upper)
+    } else if it.get().is_some() {
+        (1, upper.and_then(|n| n.checked_add(1)))
+    } else {
+        (0, Some(0))
+    }
+}
+
+/// A streaming iterator which splits a stream into slices of owned elements, between neighbours
+/// for which a predicate returns `true`.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub struct SplitWhen<I, F>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    it: I,
+    f: F,
+    // slots past `len` are kept to reuse their allocations
+    buf: Vec<<I::Item as ToOwned>::Owned>,
+    len: usize,
+    started: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> SplitWhen<I, F>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    #[inline]
+    pub(crate) fn new(it: I, f: F) -> SplitWhen<I, F> {
+        SplitWhen {
+            it,
+            f,
+            buf: vec![],
+            len: 0,
+            started: false,
+        }
+    }
+}
+
+/// Stores an owned copy of `item` in slot `len` of `buf`, reusing the slot's allocation if it
+/// exists.
+#[cfg(feature = "alloc")]
+#[inline]
+pub(crate) fn push_owned<T>(buf: &mut Vec<T::Owned>, len: &mut usize, item: &T)
+where
+    T: ?Sized + ToOwned,
+{
+    if *len < buf.len() {
+        item.clone_into(&mut buf[*len]);
+    } else {
+        buf.push(item.to_owned());
+    }
+    *len += 1;
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> StreamingIterator for SplitWhen<I, F>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+    F: FnMut(&I::Item, &I::Item) -> bool,
+{
+    type Item = [<I::Item as ToOwned>::Owned];
+
+    #[inline]
+    fn advance(&mut self) {
+        if !self.started {
+            self.started = true;
+            self.it.advance();
+        }
+
+        self.len = 0;
+        while let Some(item) = self.it.get() {
+            if self.len > 0 && (self.f)(self.buf[self.len - 1].borrow(), item) {
+                break;
+            }
+            push_owned(&mut self.buf, &mut self.len, item);
+            self.it.advance();
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[<I::Item as ToOwned>::Owned]> {
+        if self.len > 0 {
+            Some(&self.buf[..self.len])
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        runs_hint(&self.it, self.started)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> fmt::Debug for SplitWhen<I, F>
+where
+    I: StreamingIterator + fmt::Debug,
+    I::Item: ToOwned,
+    <I::Item as ToOwned>::Owned: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SplitWhen")
+            .field("it", &self.it)
+            .field("buf", &&self.buf[..self.len])
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "alloc")]
+    use alloc::borrow::ToOwned;
+    #[cfg(feature = "alloc")]
+    use alloc::string::String;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+
+    use super::super::*;
+
+    #[test]
+    fn coalesce() {
+        let items = [1, 2, 10, 3, 20, 30, 4];
+        let mut it = convert(items.iter().cloned()).coalesce(
+            |acc: &mut i32, &i| *acc = i,
+            |acc, &i| {
+                if i < 10 {
+                    return false;
+                }
+                *acc += i;
+                true
+            },
+        );
+        assert_eq!(it.size_hint(), (1, Some(7)));
+        assert_eq!(it.next(), Some(&1));
+        assert_eq!(it.size_hint(), (1, Some(6)));
+        assert_eq!(it.next(), Some(&12));
+        assert_eq!(it.next(), Some(&53));
+        assert_eq!(it.next(), Some(&4));
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next(), None);
+
+        let items: [i32; 0] = [];
+        let mut it = convert(items.iter()).coalesce(|_: &mut i32, _| {}, |_, _| true);
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn coalesce_lines() {
+        let lines = ["a", " b", " c", "d", "e", " f"];
+        let joined = convert_ref(lines.iter().cloned())
+            .coalesce(
+                |acc: &mut String, line| {
+                    acc.clear();
+                    acc.push_str(line);
+                },
+                |acc, line| {
+                    if !line.starts_with(' ') {
+                        return false;
+                    }
+                    acc.push_str(line);
+                    true
+                },
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(joined, ["a b c", "d", "e f"]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn split_when() {
+        let words = ["apple", "avocado", "banana", "blueberry", "cherry"];
+        let mut it = convert_ref(words.iter().cloned()).split_when(|a, b| a[..1] != b[..1]);
+        assert_eq!(it.size_hint(), (1, Some(5)));
+        assert_eq!(
+            it.next(),
+            Some(&["apple".to_owned(), "avocado".to_owned()][..])
+        );
+        assert_eq!(it.size_hint(), (1, Some(3)));
+        assert_eq!(
+            it.next(),
+            Some(&["banana".to_owned(), "blueberry".to_owned()][..])
+        );
+        assert_eq!(it.next(), Some(&["cherry".to_owned()][..]));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next(), None);
+
+        let items = [1, 2, 3, 2, 3, 1];
+        let runs = convert(items.iter().cloned())
+            .split_when(|a, b| b < a)
+            .map_deref(|run| run.to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(runs, [vec![1, 2, 3], vec![2, 3], vec![1]]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn group_by() {
+        let items = [1, 3, 2, 4, 6, 5, 8];
+        let mut groups = convert(items.iter().cloned()).group_by_key(|i| match i % 2 {
+            0 => "even",
+            _ => "odd",
+        });
+
+        let mut actual = vec![];
+        while let Some(mut group) = groups.next_group() {
+            let key = *group.key();
+            let mut members = vec![];
+            while let Some(&i) = group.next() {
+                members.push(i);
+            }
+            actual.push((key, members));
+        }
+        assert_eq!(
+            actual,
+            [
+                ("odd", vec![1, 3]),
+                ("even", vec![2, 4, 6]),
+                ("odd", vec![5]),
+                ("even", vec![8]),
+            ]
+        );
+        assert!(groups.next().is_none());
+        assert!(groups.next().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn skip_groups() {
+        let lines = ["a 1", "a 2", "a 3", "b 1", "c 1", "c 2"];
+        let mut groups = convert_ref(lines.iter().cloned()).group_by(|l| &l[..1]);
+
+        assert_eq!(groups.next(), Some("a"));
+        {
+            let mut group = groups.group().unwrap();
+            assert_eq!(group.next(), Some("a 1"));
+        }
+        assert_eq!(groups.next(), Some("b"));
+        assert_eq!(groups.next(), Some("c"));
+        {
+            let mut group = groups.group().unwrap();
+            assert_eq!(group.next(), Some("c 1"));
+        }
+        {
+            // a new handle resumes where the previous one left off
+            let mut group = groups.group().unwrap();
+            assert_eq!(group.next(), Some("c 2"));
+            assert_eq!(group.next(), None);
+            assert_eq!(group.next(), None);
+        }
+        assert_eq!(groups.next(), None);
+        assert!(groups.group().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn group_size_hint() {
+        let items = [1, 1, 1, 1];
+        let mut groups = convert(items.iter()).group_by(|i| *i);
+
+        assert_eq!(groups.next(), Some(&1));
+        let mut group = groups.group().unwrap();
+        assert_eq!(group.size_hint(), (0, Some(4)));
+        assert_eq!(group.next(), Some(&&1));
+        assert_eq!(group.size_hint(), (0, Some(3)));
+        assert_eq!(group.by_ref().count(), 3);
+        assert_eq!(group.next(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn count_groups() {
+        let items: [u8; 0] = [];
+        assert_eq!(convert(items.iter()).group_by(|i| *i).count(), 0);
+
+        let items = [1, 1, 2, 2, 2, 3];
+        assert_eq!(convert(items.iter()).group_by(|i| *i).count(), 3);
+    }
+}
@@ -28,6 +28,8 @@
 //! }
 //! ```
 //!
+//! The `streaming_for!` macro binds the iterator once and so cannot make this mistake.
+//!
 //! While the standard `Iterator` trait's functionality is based off of the `next` method,
 //! `StreamingIterator`'s functionality is based off of a pair of methods: `advance` and `get`. This
 //! essentially splits the logic of `next` in half (in fact, `StreamingIterator`'s `next` method
@@ -43,8 +45,74 @@
 #[cfg(feature = "std")]
 extern crate core;
 
+#[cfg(feature = "alloc")]
+#[macro_use]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::ToOwned;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
 use core::cmp;
+use core::fmt;
+use core::iter;
 use core::marker::PhantomData;
+use core::ops::Deref;
+
+#[macro_use]
+mod macros;
+
+mod checked;
+mod collect;
+#[cfg(feature = "alloc")]
+mod combinatorics;
+#[cfg(feature = "std")]
+mod csv;
+mod group;
+mod merge;
+#[cfg(feature = "alloc")]
+mod packed;
+#[cfg(feature = "alloc")]
+mod parts;
+#[cfg(feature = "std")]
+mod records;
+#[cfg(feature = "alloc")]
+mod replay;
+mod scratch;
+#[cfg(feature = "std")]
+mod sort;
+#[cfg(feature = "std")]
+mod thread;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "std")]
+mod walk;
+
+pub use checked::*;
+pub use collect::*;
+#[cfg(feature = "alloc")]
+pub use combinatorics::*;
+#[cfg(feature = "std")]
+pub use csv::*;
+pub use group::*;
+pub use merge::*;
+#[cfg(feature = "alloc")]
+pub use packed::*;
+#[cfg(feature = "alloc")]
+pub use parts::*;
+#[cfg(feature = "std")]
+pub use records::*;
+#[cfg(feature = "alloc")]
+pub use replay::*;
+pub use scratch::*;
+#[cfg(feature = "std")]
+pub use sort::*;
+#[cfg(feature = "std")]
+pub use thread::*;
+#[cfg(feature = "std")]
+pub use walk::*;
 
 /// An interface for dealing with streaming iterators.
 pub trait StreamingIterator {
@@ -108,6 +176,20 @@ pub trait StreamingIterator {
         !self.all(|i| !f(i))
     }
 
+    /// Creates an iterator which yields the elements of this iterator whose keys do not appear in
+    /// another, where both iterators are sorted by key.
+    ///
+    /// `cmp` compares the keys of an element of each iterator. Keys may repeat in this iterator.
+    #[inline]
+    fn anti_join<J, F>(self, other: J, cmp: F) -> AntiJoin<Self, J, F>
+    where
+        Self: Sized,
+        J: StreamingIterator,
+        F: FnMut(&Self::Item, &J::Item) -> cmp::Ordering,
+    {
+        AntiJoin::new(self, other, cmp)
+    }
+
     /// Borrows an iterator, rather than consuming it.
     ///
     /// This is useful to allow the application of iterator adaptors while still retaining ownership
@@ -134,6 +216,21 @@ pub trait StreamingIterator {
         }
     }
 
+    /// Creates an iterator which panics with a descriptive message when the iterator protocol is
+    /// broken.
+    ///
+    /// The returned iterator panics if `get` is called before the first `advance`, if it is
+    /// advanced again after reaching its end, or if this iterator's `size_hint` is contradicted
+    /// by the elements it actually produces. Wrap the returned iterator in `fuse` where advancing
+    /// past the end is intended.
+    #[inline]
+    fn checked(self) -> Checked<Self>
+    where
+        Self: Sized,
+    {
+        Checked::new(self)
+    }
+
     /// Produces a normal, non-streaming, iterator by cloning the elements of this iterator.
     #[inline]
     fn cloned(self) -> Cloned<Self>
@@ -144,6 +241,75 @@ pub trait StreamingIterator {
         Cloned(self)
     }
 
+    /// Creates an iterator which merges runs of adjacent elements into an accumulator.
+    ///
+    /// `init` resets the accumulator from the first element of a run, and `merge` adds each
+    /// following element to it, returning `false` if the element instead starts a new run. The
+    /// same accumulator, created with `A::default()`, is reused for every run.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// let readings = [(1, 5), (1, 2), (2, 4), (1, 1)];
+    /// let mut totals = convert(readings.iter().cloned()).coalesce(
+    ///     |acc: &mut (i32, i32), &r| *acc = r,
+    ///     |acc, &(key, value)| {
+    ///         if key != acc.0 {
+    ///             return false;
+    ///         }
+    ///         acc.1 += value;
+    ///         true
+    ///     },
+    /// );
+    /// assert_eq!(totals.next(), Some(&(1, 7)));
+    /// assert_eq!(totals.next(), Some(&(2, 4)));
+    /// assert_eq!(totals.next(), Some(&(1, 1)));
+    /// assert_eq!(totals.next(), None);
+    /// ```
+    #[inline]
+    fn coalesce<A, N, M>(self, init: N, merge: M) -> Coalesce<Self, A, N, M>
+    where
+        Self: Sized,
+        A: Default,
+        N: FnMut(&mut A, &Self::Item),
+        M: FnMut(&mut A, &Self::Item) -> bool,
+    {
+        Coalesce::new(self, init, merge)
+    }
+
+    /// Consumes the iterator, building a collection from its elements.
+    ///
+    /// ```
+    /// # use streaming_iterator::{convert_ref, StreamingIterator};
+    /// let words = ["stream", "ing"];
+    /// # #[cfg(feature = "std")] {
+    /// let joined: String = convert_ref(words.iter().cloned()).collect();
+    /// assert_eq!(joined, "streaming");
+    /// let owned: Vec<String> = convert_ref(words.iter().cloned()).collect();
+    /// assert_eq!(owned, ["stream", "ing"]);
+    /// # }
+    /// ```
+    #[inline]
+    fn collect<B>(self) -> B
+    where
+        Self: Sized,
+        B: FromStreamingIterator<Self::Item>,
+    {
+        B::from_streaming_iter(self)
+    }
+
+    /// Consumes the iterator, copying its elements back to back into a single buffer.
+    ///
+    /// Requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn collect_packed(self) -> PackedVec<Self::Item>
+    where
+        Self: Sized,
+        Self::Item: PackedItem,
+    {
+        self.collect()
+    }
+
     /// Consumes the iterator, counting the number of remaining elements and returning it.
     #[inline]
     fn count(self) -> usize
@@ -153,6 +319,34 @@ pub trait StreamingIterator {
         self.fold(0, |count, _| count + 1)
     }
 
+    /// Calls a closure on each difference between this iterator and another, where both
+    /// iterators are sorted by key.
+    ///
+    /// This iterator holds the old elements and `other` the new ones. `cmp` compares the keys of an
+    /// element of each iterator and `eq` determines if two elements with equal keys are unchanged.
+    #[inline]
+    fn diff<J, C, E, F>(self, other: J, cmp: C, mut eq: E, mut f: F)
+    where
+        Self: Sized,
+        J: StreamingIterator,
+        C: FnMut(&Self::Item, &J::Item) -> cmp::Ordering,
+        E: FnMut(&Self::Item, &J::Item) -> bool,
+        F: FnMut(Diff<&Self::Item, &J::Item>),
+    {
+        let mut it = self.merge_join_by(other, cmp);
+        while let Some(joined) = it.next() {
+            match joined.items() {
+                EitherOrBoth::Left(a) => f(Diff::Removed(a)),
+                EitherOrBoth::Right(b) => f(Diff::Added(b)),
+                EitherOrBoth::Both(a, b) => {
+                    if !eq(a, b) {
+                        f(Diff::Changed(a, b))
+                    }
+                }
+            }
+        }
+    }
+
     /// Creates an iterator which uses a closure to determine if an element should be yielded.
     #[inline]
     fn filter<F>(self, f: F) -> Filter<Self, F>
@@ -160,7 +354,7 @@ pub trait StreamingIterator {
         Self: Sized,
         F: FnMut(&Self::Item) -> bool,
     {
-        Filter { it: self, f: f }
+        Filter { it: self, f }
     }
 
     /// Creates an iterator which both filters and maps by applying a closure to elements.
@@ -172,7 +366,7 @@ pub trait StreamingIterator {
     {
         FilterMap {
             it: self,
-            f: f,
+            f,
             item: None,
         }
     }
@@ -193,6 +387,67 @@ pub trait StreamingIterator {
         }
     }
 
+    /// Creates an iterator which flattens normal, non-streaming, iterators obtained by applying a
+    /// closure to elements, lending out each of their elements in turn.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// let mut it = convert(vec![2, 3]).flat_map_iter(|&n| 0..n);
+    /// assert_eq!(it.next(), Some(&0));
+    /// assert_eq!(it.next(), Some(&1));
+    /// assert_eq!(it.next(), Some(&0));
+    /// ```
+    #[inline]
+    fn flat_map_iter<U, F>(self, f: F) -> FlatMapIter<Self, U::IntoIter, F>
+    where
+        Self: Sized,
+        U: IntoIterator,
+        F: FnMut(&Self::Item) -> U,
+    {
+        FlatMapIter {
+            it: self,
+            f,
+            sub_iter: None,
+            done: false,
+        }
+    }
+
+    /// Creates an iterator which yields borrowed parts of each element, such as the fields of a
+    /// line, as chosen by a closure.
+    ///
+    /// Like `map_ref`, this lends out references into the original elements. The closure receives
+    /// each element along with `emit`, and passes `emit` the parts of the element to yield, in
+    /// order. Each part is recorded as its position within the element, which must be a `str` or
+    /// a slice; a slice passed to `emit` which does not lie within the element, such as a string
+    /// literal, cannot be lent out once the closure returns and is skipped.
+    ///
+    /// The closure does not simply return an iterator over the parts, as `map_ref`'s returns a
+    /// reference, because such an iterator, like `str::Split`, borrows the element: its type
+    /// depends on the lifetime of the closure's argument, which a closure's return type cannot
+    /// express. Passing `emit` to `Iterator::for_each` is the equivalent.
+    ///
+    /// Requires the `alloc` feature.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert_ref};
+    /// let lines = ["a b", "c"];
+    /// let mut words = convert_ref(lines.iter().cloned())
+    ///     .flat_map_ref(|line, emit| line.split(' ').for_each(emit));
+    /// assert_eq!(words.next(), Some("a"));
+    /// assert_eq!(words.next(), Some("b"));
+    /// assert_eq!(words.next(), Some("c"));
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn flat_map_ref<U, F>(self, f: F) -> FlatMapRef<Self, U, F>
+    where
+        Self: Sized,
+        Self::Item: AsRef<[U]> + core::ops::Index<core::ops::Range<usize>, Output = Self::Item>,
+        F: for<'a> FnMut(&'a Self::Item, &mut dyn FnMut(&'a Self::Item)),
+    {
+        FlatMapRef::new(self, f)
+    }
+
     /// Creates a regular, non-streaming iterator which both filters and maps by applying a closure to elements.
     #[inline]
     fn filter_map_deref<B, F>(self, f: F) -> FilterMapDeref<Self, F>
@@ -239,6 +494,70 @@ pub trait StreamingIterator {
         }
     }
 
+    /// Creates an iterator which groups consecutive elements with equal keys, as determined by a
+    /// closure returning a reference to the key of an element.
+    ///
+    /// The iterator yields the key of each group, and the elements of the current group are
+    /// available through `GroupBy::group`. Keys are compared by `PartialEq` against an owned copy
+    /// of the key of the current group, so unsized keys such as `str` are supported. Keys which
+    /// are computed from an element rather than borrowed from it are grouped by `group_by_key`.
+    ///
+    /// Requires the `alloc` feature.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert_ref};
+    /// let lines = ["req-1 start", "req-1 end", "req-2 start"];
+    /// let mut groups = convert_ref(lines.iter().cloned()).group_by(|l| l.split(' ').next().unwrap());
+    /// while let Some(mut group) = groups.next_group() {
+    ///     println!("{}:", group.key());
+    ///     while let Some(line) = group.next() {
+    ///         println!("  {}", line);
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn group_by<K, F>(self, f: F) -> GroupBy<Self, K, F>
+    where
+        Self: Sized,
+        K: ?Sized + ToOwned + PartialEq,
+        F: FnMut(&Self::Item) -> &K,
+    {
+        GroupBy::new(self, f)
+    }
+
+    /// Creates an iterator which groups consecutive elements with equal keys, as determined by a
+    /// closure computing the key of an element.
+    ///
+    /// This is the same as `group_by`, except that the key is returned by value, so it need not
+    /// be borrowed from the element. The key of the current group is kept and compared by
+    /// `PartialEq` with that of each following element.
+    ///
+    /// Requires the `alloc` feature.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// let readings = [3, 8, 12, 17, 15, 4];
+    /// let mut groups = convert(readings.iter().cloned()).group_by_key(|&r| r >= 10);
+    /// let mut sizes = [0; 3];
+    /// let mut n = 0;
+    /// while let Some(group) = groups.next_group() {
+    ///     sizes[n] = group.count();
+    ///     n += 1;
+    /// }
+    /// assert_eq!(sizes, [2, 3, 1]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn group_by_key<K, F>(self, f: F) -> GroupBy<Self, K, ByValue<F>>
+    where
+        Self: Sized,
+        K: Clone + PartialEq,
+        F: FnMut(&Self::Item) -> K,
+    {
+        GroupBy::new(self, ByValue::new(f))
+    }
+
     /// Call a closure on each element, passing the element on.
     /// The closure is called upon calls to `advance` or `advance_back`, and exactly once per element
     /// regardless of how many times (if any) `get` is called.
@@ -251,6 +570,44 @@ pub trait StreamingIterator {
         Inspect { it: self, f }
     }
 
+    /// Calls a closure on each pair of elements of this iterator and another which have equal keys,
+    /// where both iterators are sorted by key.
+    ///
+    /// `cmp` compares the keys of an element of each iterator. As with `merge_join_by`, keys may
+    /// repeat in this iterator, each such element being paired with the same element of `other`,
+    /// but only the first element of `other` with a given key is joined.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// let orders = [(1, "tea"), (1, "cake"), (3, "soup")];
+    /// let customers = [(1, "ann"), (2, "bob"), (3, "cy")];
+    /// let mut n = 0;
+    /// convert(orders.iter().cloned()).inner_join_for_each(
+    ///     convert(customers.iter().cloned()),
+    ///     |order, customer| order.0.cmp(&customer.0),
+    ///     |order, customer| {
+    ///         println!("{} ordered {}", customer.1, order.1);
+    ///         n += 1;
+    ///     },
+    /// );
+    /// assert_eq!(n, 3);
+    /// ```
+    #[inline]
+    fn inner_join_for_each<J, C, F>(self, other: J, cmp: C, mut f: F)
+    where
+        Self: Sized,
+        J: StreamingIterator,
+        C: FnMut(&Self::Item, &J::Item) -> cmp::Ordering,
+        F: FnMut(&Self::Item, &J::Item),
+    {
+        let mut it = self.merge_join_by(other, cmp);
+        while let Some(joined) = it.next() {
+            if let EitherOrBoth::Both(a, b) = joined.items() {
+                f(a, b);
+            }
+        }
+    }
+
     /// Creates an iterator which transforms elements of this iterator by passing them to a closure.
     #[inline]
     fn map<B, F>(self, f: F) -> Map<Self, B, F>
@@ -260,11 +617,35 @@ pub trait StreamingIterator {
     {
         Map {
             it: self,
-            f: f,
+            f,
             item: None,
         }
     }
 
+    /// Creates an iterator which writes each element into a reused byte buffer with a closure,
+    /// yielding the bytes written.
+    ///
+    /// The buffer is cleared before each call to `f`, which may use the `io::Write` implementation
+    /// of `Vec<u8>`. An error returned by `f` ends iteration and is reported by `MapBytes::error`.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// # use std::io::Write;
+    /// let mut it = convert(vec![1u16, 2]).map_bytes(|i, buf| buf.write_all(&i.to_be_bytes()));
+    /// assert_eq!(it.next(), Some(&[0, 1][..]));
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    fn map_bytes<F>(self, f: F) -> MapBytes<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Item, &mut Vec<u8>) -> std::io::Result<()>,
+    {
+        MapBytes::new(self, f)
+    }
+
     /// Creates a regular, non-streaming iterator which transforms elements of this iterator by passing them to a closure.
     #[inline]
     fn map_deref<B, F>(self, f: F) -> MapDeref<Self, F>
@@ -275,6 +656,60 @@ pub trait StreamingIterator {
         MapDeref { it: self, f }
     }
 
+    /// Creates an iterator which formats each element into a reused `String` with a closure,
+    /// yielding the formatted text.
+    ///
+    /// The string is cleared before each call to `f`, which may use the `fmt::Write`
+    /// implementation of `String`.
+    ///
+    /// Requires the `alloc` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns an error, as `ToString::to_string` does.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// # use std::fmt::Write;
+    /// let mut it = convert(vec![1, 2]).map_fmt(|i, buf| write!(buf, "item {}", i));
+    /// assert_eq!(it.next(), Some("item 1"));
+    /// assert_eq!(it.next(), Some("item 2"));
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn map_fmt<F>(self, f: F) -> MapFmt<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Item, &mut String) -> core::fmt::Result,
+    {
+        MapFmt::new(self, f)
+    }
+
+    /// Creates an iterator which renders each element into a reused buffer with a closure,
+    /// yielding a reference to the buffer.
+    ///
+    /// Unlike `map`, no new value is created for each element: the buffer, starting from `buf`,
+    /// is cleared before `f` is called with each element, so that only its allocation is reused.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// # #[cfg(feature = "alloc")]
+    /// # {
+    /// let mut it = convert(vec![3, 4]).map_into(vec![], |&i, buf| buf.extend_from_slice(&[i, i * i]));
+    /// assert_eq!(it.next().map(|b| &b[..]), Some(&[3, 9][..]));
+    /// assert_eq!(it.next().map(|b| &b[..]), Some(&[4, 16][..]));
+    /// # }
+    /// ```
+    #[inline]
+    fn map_into<B, F>(self, buf: B, f: F) -> MapInto<Self, B, F>
+    where
+        Self: Sized,
+        B: Clear,
+        F: FnMut(&Self::Item, &mut B),
+    {
+        MapInto::new(self, buf, f)
+    }
+
     /// Creates an iterator which transforms elements of this iterator by passing them to a closure.
     ///
     /// Unlike `map`, this method takes a closure that returns a reference into the original value.
@@ -284,7 +719,58 @@ pub trait StreamingIterator {
         Self: Sized,
         F: Fn(&Self::Item) -> &B,
     {
-        MapRef { it: self, f: f }
+        MapRef { it: self, f }
+    }
+
+    /// Creates an iterator which joins this iterator with another, where both iterators are sorted
+    /// by key.
+    ///
+    /// `cmp` compares the keys of an element of each iterator. Each element of the returned
+    /// iterator borrows the current element of one or both iterators: those of an iterator whose
+    /// key is smaller than the other's are yielded alone, and those with equal keys are yielded
+    /// together. Neither iterator's elements are cloned.
+    ///
+    /// Keys may repeat in this iterator: since the element of `other` is kept until this iterator
+    /// moves past its key, every element of this iterator with that key is yielded together with
+    /// it. Joining repeated keys on both sides would require buffering, so keys are expected to be
+    /// unique in `other`; any further elements of `other` with an already joined key are yielded
+    /// alone.
+    #[inline]
+    fn merge_join_by<J, F>(self, other: J, cmp: F) -> MergeJoinBy<Self, J, F>
+    where
+        Self: Sized,
+        J: StreamingIterator,
+        F: FnMut(&Self::Item, &J::Item) -> cmp::Ordering,
+    {
+        MergeJoinBy::new(self, other, cmp)
+    }
+
+    /// Creates an iterator which merges this iterator with another, both sorted according to a
+    /// comparison function, into a single sorted stream.
+    ///
+    /// Items which compare equal are yielded from this iterator first.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert_ref};
+    /// let a = ["fig", "apple", "banana"];
+    /// let b = ["date", "cherry"];
+    /// let mut merged = convert_ref(a.iter().cloned())
+    ///     .merge_by(convert_ref(b.iter().cloned()), |x, y| x.len().cmp(&y.len()));
+    /// assert_eq!(merged.next(), Some("fig"));
+    /// assert_eq!(merged.next(), Some("date"));
+    /// assert_eq!(merged.next(), Some("apple"));
+    /// assert_eq!(merged.next(), Some("banana"));
+    /// assert_eq!(merged.next(), Some("cherry"));
+    /// assert_eq!(merged.next(), None);
+    /// ```
+    #[inline]
+    fn merge_by<J, F>(self, other: J, f: F) -> MergeBy<Self, J, F>
+    where
+        Self: Sized,
+        J: StreamingIterator<Item = Self::Item>,
+        F: FnMut(&Self::Item, &Self::Item) -> cmp::Ordering,
+    {
+        MergeBy::new(self, other, f)
     }
 
     /// Consumes the first `n` elements of the iterator, returning the next one.
@@ -292,9 +778,7 @@ pub trait StreamingIterator {
     fn nth(&mut self, n: usize) -> Option<&Self::Item> {
         for _ in 0..n {
             self.advance();
-            if self.get().is_none() {
-                return None;
-            }
+            self.get()?;
         }
         self.next()
     }
@@ -302,8 +786,8 @@ pub trait StreamingIterator {
     /// Creates a normal, non-streaming, iterator with elements produced by calling `to_owned` on
     /// the elements of this iterator.
     ///
-    /// Requires the `std` feature.
-    #[cfg(feature = "std")]
+    /// Requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
     #[inline]
     fn owned(self) -> Owned<Self>
     where
@@ -313,6 +797,101 @@ pub trait StreamingIterator {
         Owned(self)
     }
 
+    /// Creates an iterator which advances this iterator on a background thread, copying its
+    /// elements into a pool of `n_buffers` owned buffers.
+    ///
+    /// The background thread fills spare buffers while the current element is borrowed, and
+    /// buffers are reused with `clone_into` once the consumer has moved past them. Dropping the
+    /// returned iterator stops the background thread, and a panic on the background thread is
+    /// resumed on the consuming thread when it next advances.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_buffers` is 0.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// let mut it = convert((0..3).map(|i| format!("line {}", i))).prefetch(2);
+    /// assert_eq!(it.next().map(String::as_str), Some("line 0"));
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    fn prefetch(self, n_buffers: usize) -> Prefetch<Self::Item>
+    where
+        Self: Sized + Send + 'static,
+        Self::Item: ToOwned,
+        <Self::Item as ToOwned>::Owned: Send + 'static,
+    {
+        thread::prefetch(self, n_buffers)
+    }
+
+    /// Calls a closure on each element on a pool of worker threads, handing elements to the
+    /// workers in batches of owned copies.
+    ///
+    /// Batches of up to `batch_size` elements are copied with `ToOwned` on the calling thread,
+    /// which advances this iterator, and processed by `threads` scoped worker threads. Batch
+    /// buffers are recycled once processed, so steady state operation does not allocate. A panic
+    /// in `f` is resumed on the calling thread.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` or `threads` is 0.
+    #[cfg(feature = "std")]
+    #[inline]
+    fn par_for_each_batched<F>(self, batch_size: usize, threads: usize, f: F)
+    where
+        Self: Sized,
+        Self::Item: ToOwned,
+        <Self::Item as ToOwned>::Owned: Send,
+        F: Fn(&Self::Item) + Sync,
+    {
+        self.par_map_batched(batch_size, threads, f, |results| {
+            while results.next().is_some() {}
+        })
+    }
+
+    /// Transforms each element with a closure on a pool of worker threads, passing a streaming
+    /// iterator over the results, in the order of the elements, to `consume`.
+    ///
+    /// Elements are batched as by `par_for_each_batched`. The worker threads borrow `f`, so they
+    /// only run for the duration of this call; the calling thread advances this iterator as
+    /// `consume` advances the results. Returns the value returned by `consume`.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` or `threads` is 0.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert};
+    /// let total = convert(1..=100u64).par_map_batched(16, 4, |&i| i * i, |squares| {
+    ///     let mut total = 0;
+    ///     while let Some(square) = squares.next() {
+    ///         total += square;
+    ///     }
+    ///     total
+    /// });
+    /// assert_eq!(total, 338350);
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    fn par_map_batched<B, F, C, R>(self, batch_size: usize, threads: usize, f: F, consume: C) -> R
+    where
+        Self: Sized,
+        Self::Item: ToOwned,
+        <Self::Item as ToOwned>::Owned: Send,
+        B: Send,
+        F: Fn(&Self::Item) -> B + Sync,
+        C: FnOnce(&mut ParMapBatched<Self, B>) -> R,
+    {
+        thread::par_map_batched(self, batch_size, threads, f, consume)
+    }
+
     /// Returns the index of the first element of the iterator matching a predicate.
     #[inline]
     fn position<F>(&mut self, mut f: F) -> Option<usize>
@@ -332,13 +911,41 @@ pub trait StreamingIterator {
         None
     }
 
+    /// Creates an iterator which records a copy of each element as it is yielded, so the elements
+    /// can be streamed again with `Recorded::replay`.
+    ///
+    /// Elements are copied back to back into a single `PackedVec`.
+    ///
+    /// ```
+    /// # use streaming_iterator::{convert_ref, StreamingIterator};
+    /// let lines = ["3", "1", "2"];
+    /// let mut it = convert_ref(lines.iter().cloned()).record();
+    /// let max = it.by_ref().fold(0, |max, l| max.max(l.parse::<u32>().unwrap()));
+    /// let scaled = it.replay().fold(vec![], |mut v, l| {
+    ///     v.push(l.parse::<u32>().unwrap() * 100 / max);
+    ///     v
+    /// });
+    /// assert_eq!(scaled, [100, 33, 66]);
+    /// ```
+    ///
+    /// Requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn record(self) -> Recorded<Self>
+    where
+        Self: Sized,
+        Self::Item: PackedItem,
+    {
+        Recorded::new(self)
+    }
+
     /// Creates an iterator which skips the first `n` elements.
     #[inline]
     fn skip(self, n: usize) -> Skip<Self>
     where
         Self: Sized,
     {
-        Skip { it: self, n: n }
+        Skip { it: self, n }
     }
 
     /// Creates an iterator that skips initial elements matching a predicate.
@@ -350,11 +957,75 @@ pub trait StreamingIterator {
     {
         SkipWhile {
             it: self,
-            f: f,
+            f,
             done: false,
         }
     }
 
+    /// Sorts the elements of the iterator, spilling sorted runs to temporary files in `tmp_dir`
+    /// whenever the buffered elements exceed `mem_budget` bytes.
+    ///
+    /// Each element is appended to a buffer by `serialize` as it arrives, and the size of a
+    /// buffered element is counted as the length of its serialized form plus the size of its
+    /// owned type. The returned iterator merges the runs back together, decoding each element with
+    /// `deserialize`. The sort is stable.
+    ///
+    /// At most 16 run files are read at once: if more runs are spilled, they are merged in groups
+    /// of 16 into longer runs, in as many passes as needed, before the returned iterator is
+    /// created.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// ```
+    /// # use streaming_iterator::{StreamingIterator, convert_ref};
+    /// # use std::env;
+    /// let words = ["pear", "apple", "fig"];
+    /// let mut sorted = convert_ref(words.iter().cloned())
+    ///     .sort_external(
+    ///         |word, buf| buf.extend_from_slice(word.as_bytes()),
+    ///         |buf| String::from_utf8(buf.to_vec()).unwrap(),
+    ///         64 << 20,
+    ///         &env::temp_dir(),
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(sorted.next().map(|s| &s[..]), Some("apple"));
+    /// ```
+    #[cfg(feature = "std")]
+    fn sort_external<S, D>(
+        self,
+        serialize: S,
+        deserialize: D,
+        mem_budget: usize,
+        tmp_dir: &std::path::Path,
+    ) -> std::io::Result<ExternalSort<<Self::Item as ToOwned>::Owned, D>>
+    where
+        Self: Sized,
+        Self::Item: ToOwned,
+        <Self::Item as ToOwned>::Owned: Ord,
+        S: FnMut(&Self::Item, &mut Vec<u8>),
+        D: FnMut(&[u8]) -> <Self::Item as ToOwned>::Owned,
+    {
+        sort::sort_external(self, serialize, deserialize, mem_budget, tmp_dir)
+    }
+
+    /// Creates an iterator which yields slices of consecutive elements, starting a new slice
+    /// between neighbours `a` and `b` whenever `f(a, b)` returns `true`.
+    ///
+    /// Elements are converted with `to_owned` into a buffer which is reused for every slice, with
+    /// `clone_into` reusing the allocations of elements from earlier slices.
+    ///
+    /// Requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn split_when<F>(self, f: F) -> SplitWhen<Self, F>
+    where
+        Self: Sized,
+        Self::Item: ToOwned,
+        F: FnMut(&Self::Item, &Self::Item) -> bool,
+    {
+        SplitWhen::new(self, f)
+    }
+
     /// Creates an iterator which only returns the first `n` elements.
     #[inline]
     fn take(self, n: usize) -> Take<Self>
@@ -363,7 +1034,7 @@ pub trait StreamingIterator {
     {
         Take {
             it: self,
-            n: n,
+            n,
             done: false,
         }
     }
@@ -377,11 +1048,35 @@ pub trait StreamingIterator {
     {
         TakeWhile {
             it: self,
-            f: f,
+            f,
             done: false,
         }
     }
 
+    /// Splits this iterator into two which each yield all of its elements.
+    ///
+    /// Elements pulled by one iterator are copied into a buffer shared with the other until it
+    /// reaches them; `Tee::lag` reports how far behind each iterator is. Buffers are reused with
+    /// `clone_into` once both iterators have moved past them.
+    ///
+    /// # Panics
+    ///
+    /// Advancing one iterator panics if it would need to buffer more than `capacity` elements for
+    /// the other one. The check is made before an element is pulled from this iterator, and
+    /// `Tee::try_advance` reports the condition instead of panicking, so the caller can advance
+    /// the other iterator first.
+    ///
+    /// Requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn tee(self, capacity: usize) -> (Tee<Self>, Tee<Self>)
+    where
+        Self: Sized,
+        Self::Item: ToOwned,
+    {
+        Tee::new(self, capacity)
+    }
+
     /// Creates an iterator which returns elemens in the opposite order.
     #[inline]
     fn rev(self) -> Rev<Self>
@@ -416,7 +1111,7 @@ pub trait StreamingIterator {
     }
 }
 
-impl<'a, I: ?Sized> StreamingIterator for &'a mut I
+impl<I: ?Sized> StreamingIterator for &mut I
 where
     I: StreamingIterator,
 {
@@ -443,7 +1138,7 @@ where
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(feature = "alloc")]
 impl<I: ?Sized> StreamingIterator for Box<I>
 where
     I: StreamingIterator,
@@ -537,6 +1232,56 @@ where
     }
 }
 
+/// Turns an iterator of owned values, such as `String`s, into a streaming iterator over the
+/// targets they dereference to.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, convert_deref, convert_ref};
+/// let owned = vec!["apple".to_owned(), "banana".to_owned()];
+/// let borrowed = ["cherry"];
+/// let mut it = convert_deref(owned).chain(convert_ref(borrowed.iter().cloned()));
+/// assert_eq!(it.next(), Some("apple"));
+/// assert_eq!(it.next(), Some("banana"));
+/// assert_eq!(it.next(), Some("cherry"));
+/// ```
+#[inline]
+pub fn convert_deref<I>(iterator: I) -> ConvertDeref<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: Deref,
+{
+    ConvertDeref {
+        it: iterator.into_iter(),
+        item: None,
+    }
+}
+
+/// Turns a normal, non-streaming, iterator of streaming iterators into a single streaming iterator
+/// yielding their elements in sequence.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, convert_ref, flatten};
+/// let lines = [&["a", "b"][..], &[], &["c"]];
+/// let mut it = flatten(lines.iter().map(|l| convert_ref(l.iter().cloned())));
+/// assert_eq!(it.next(), Some("a"));
+/// assert_eq!(it.next(), Some("b"));
+/// assert_eq!(it.next(), Some("c"));
+/// assert_eq!(it.next(), None);
+/// ```
+#[inline]
+pub fn flatten<I>(iterators: I) -> Flatten<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: StreamingIterator,
+{
+    Flatten {
+        it: iterators.into_iter().fuse(),
+        front: None,
+        back: None,
+        end: FlattenEnd::Front,
+    }
+}
+
 /// A simple iterator that returns nothing
 #[derive(Clone, Debug)]
 pub struct Empty<I> {
@@ -698,7 +1443,7 @@ where
 
     #[inline]
     fn next(&mut self) -> Option<I::Item> {
-        self.0.next().map(Clone::clone)
+        self.0.next().cloned()
     }
 
     #[inline]
@@ -723,7 +1468,7 @@ where
 {
     #[inline]
     fn next_back(&mut self) -> Option<I::Item> {
-        self.0.next_back().map(Clone::clone)
+        self.0.next_back().cloned()
     }
 }
 
@@ -830,12 +1575,12 @@ where
     }
 
     #[inline]
-    fn fold<Acc, Fold>(self, init: Acc, mut f: Fold) -> Acc
+    fn fold<Acc, Fold>(self, init: Acc, f: Fold) -> Acc
     where
         Self: Sized,
         Fold: FnMut(Acc, &Self::Item) -> Acc,
     {
-        self.it.fold(init, move |acc, item| f(acc, item))
+        self.it.fold(init, f)
     }
 }
 
@@ -848,13 +1593,82 @@ where
         self.item = self.it.next_back();
     }
 
+    #[inline]
+    fn rfold<Acc, Fold>(self, init: Acc, f: Fold) -> Acc
+    where
+        Self: Sized,
+        Fold: FnMut(Acc, &Self::Item) -> Acc,
+    {
+        self.it.rev().fold(init, f)
+    }
+}
+
+/// A streaming iterator which yields the targets of the owned elements of a normal, non-streaming,
+/// iterator.
+#[derive(Clone, Debug)]
+pub struct ConvertDeref<I>
+where
+    I: Iterator,
+    I::Item: Deref,
+{
+    it: I,
+    item: Option<I::Item>,
+}
+
+impl<I> StreamingIterator for ConvertDeref<I>
+where
+    I: Iterator,
+    I::Item: Deref,
+{
+    type Item = <I::Item as Deref>::Target;
+
+    #[inline]
+    fn advance(&mut self) {
+        self.item = self.it.next();
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&Self::Item> {
+        self.item.as_deref()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.it.count()
+    }
+
+    #[inline]
+    fn fold<Acc, Fold>(self, init: Acc, mut f: Fold) -> Acc
+    where
+        Self: Sized,
+        Fold: FnMut(Acc, &Self::Item) -> Acc,
+    {
+        self.it.fold(init, move |acc, item| f(acc, &item))
+    }
+}
+
+impl<I> DoubleEndedStreamingIterator for ConvertDeref<I>
+where
+    I: DoubleEndedIterator,
+    I::Item: Deref,
+{
+    #[inline]
+    fn advance_back(&mut self) {
+        self.item = self.it.next_back();
+    }
+
     #[inline]
     fn rfold<Acc, Fold>(self, init: Acc, mut f: Fold) -> Acc
     where
         Self: Sized,
         Fold: FnMut(Acc, &Self::Item) -> Acc,
     {
-        self.it.rev().fold(init, move |acc, item| f(acc, item))
+        self.it.rev().fold(init, move |acc, item| f(acc, &item))
     }
 }
 
@@ -1083,6 +1897,242 @@ where
     }
 }
 
+/// A streaming iterator that maps elements to normal, non-streaming, iterators with a closure and
+/// then yields the concatenation of the obtained iterators.
+pub struct FlatMapIter<I, J, F>
+where
+    J: Iterator,
+{
+    it: I,
+    f: F,
+    sub_iter: Option<Convert<J>>,
+    done: bool,
+}
+
+impl<I, U, F> StreamingIterator for FlatMapIter<I, U::IntoIter, F>
+where
+    I: StreamingIterator,
+    F: FnMut(&I::Item) -> U,
+    U: IntoIterator,
+{
+    type Item = U::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        while !self.done {
+            if let Some(ref mut sub_iter) = self.sub_iter {
+                if sub_iter.next().is_some() {
+                    return;
+                }
+            }
+            self.sub_iter = match self.it.next() {
+                Some(item) => Some(convert((self.f)(item))),
+                None => {
+                    self.done = true;
+                    None
+                }
+            };
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&Self::Item> {
+        self.sub_iter.as_ref().and_then(Convert::get)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = match self.sub_iter {
+            Some(ref sub_iter) => sub_iter.size_hint(),
+            None => (0, Some(0)),
+        };
+        if self.done || self.it.size_hint().1 == Some(0) {
+            (lower, upper)
+        } else {
+            (lower, None)
+        }
+    }
+
+    #[inline]
+    fn fold<Acc, Fold>(self, init: Acc, mut fold: Fold) -> Acc
+    where
+        Self: Sized,
+        Fold: FnMut(Acc, &Self::Item) -> Acc,
+    {
+        let mut acc = init;
+        if let Some(sub_iter) = self.sub_iter {
+            acc = sub_iter.fold(acc, &mut fold);
+        }
+        if self.done {
+            return acc;
+        }
+        let mut f = self.f;
+        self.it.fold(acc, |acc, item| {
+            f(item)
+                .into_iter()
+                .fold(acc, |acc, item| fold(acc, &item))
+        })
+    }
+}
+
+impl<I, J, F> fmt::Debug for FlatMapIter<I, J, F>
+where
+    I: fmt::Debug,
+    J: Iterator + fmt::Debug,
+    J::Item: fmt::Debug,
+    F: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FlatMapIter")
+            .field("it", &self.it)
+            .field("f", &self.f)
+            .field("sub_iter", &self.sub_iter)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum FlattenEnd {
+    Front,
+    Back,
+}
+
+/// A streaming iterator which yields the elements of each streaming iterator produced by a normal,
+/// non-streaming, iterator in sequence.
+#[derive(Debug)]
+pub struct Flatten<I>
+where
+    I: Iterator,
+{
+    it: iter::Fuse<I>,
+    front: Option<I::Item>,
+    back: Option<I::Item>,
+    // the end from which the current element was taken
+    end: FlattenEnd,
+}
+
+impl<I> StreamingIterator for Flatten<I>
+where
+    I: Iterator,
+    I::Item: StreamingIterator,
+{
+    type Item = <I::Item as StreamingIterator>::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        self.end = FlattenEnd::Front;
+        loop {
+            if let Some(ref mut front) = self.front {
+                if front.next().is_some() {
+                    return;
+                }
+            }
+            match self.it.next() {
+                Some(front) => self.front = Some(front),
+                None => break,
+            }
+        }
+        self.front = None;
+
+        // the iterator started from the back holds the last elements
+        if let Some(ref mut back) = self.back {
+            if back.next().is_some() {
+                self.end = FlattenEnd::Back;
+                return;
+            }
+        }
+        self.back = None;
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&Self::Item> {
+        let current = match self.end {
+            FlattenEnd::Front => &self.front,
+            FlattenEnd::Back => &self.back,
+        };
+        current.as_ref().and_then(StreamingIterator::get)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let hint = |it: &Option<I::Item>| it.as_ref().map_or((0, Some(0)), I::Item::size_hint);
+        let (front_lower, front_upper) = hint(&self.front);
+        let (back_lower, back_upper) = hint(&self.back);
+        let lower = front_lower.saturating_add(back_lower);
+        match (front_upper, back_upper, self.it.size_hint()) {
+            (Some(front), Some(back), (_, Some(0))) => (lower, front.checked_add(back)),
+            _ => (lower, None),
+        }
+    }
+
+    #[inline]
+    fn fold<Acc, Fold>(self, init: Acc, mut fold: Fold) -> Acc
+    where
+        Self: Sized,
+        Fold: FnMut(Acc, &Self::Item) -> Acc,
+    {
+        let mut acc = init;
+        if let Some(front) = self.front {
+            acc = front.fold(acc, &mut fold);
+        }
+        acc = self.it.fold(acc, |acc, it| it.fold(acc, &mut fold));
+        if let Some(back) = self.back {
+            acc = back.fold(acc, &mut fold);
+        }
+        acc
+    }
+}
+
+impl<I> DoubleEndedStreamingIterator for Flatten<I>
+where
+    I: DoubleEndedIterator,
+    I::Item: DoubleEndedStreamingIterator,
+{
+    #[inline]
+    fn advance_back(&mut self) {
+        self.end = FlattenEnd::Back;
+        loop {
+            if let Some(ref mut back) = self.back {
+                if back.next_back().is_some() {
+                    return;
+                }
+            }
+            match self.it.next_back() {
+                Some(back) => self.back = Some(back),
+                None => break,
+            }
+        }
+        self.back = None;
+
+        // the iterator started from the front holds the first elements
+        if let Some(ref mut front) = self.front {
+            if front.next_back().is_some() {
+                self.end = FlattenEnd::Front;
+                return;
+            }
+        }
+        self.front = None;
+    }
+
+    #[inline]
+    fn rfold<Acc, Fold>(self, init: Acc, mut fold: Fold) -> Acc
+    where
+        Self: Sized,
+        Fold: FnMut(Acc, &Self::Item) -> Acc,
+    {
+        let mut acc = init;
+        if let Some(back) = self.back {
+            acc = back.rfold(acc, &mut fold);
+        }
+        acc = self.it.rev().fold(acc, |acc, it| it.rfold(acc, &mut fold));
+        if let Some(front) = self.front {
+            acc = front.rfold(acc, &mut fold);
+        }
+        acc
+    }
+}
+
 /// A regular, non-streaming iterator which both filters and maps elements of a streaming iterator with a closure.
 #[derive(Debug)]
 pub struct FilterMapDeref<I, F> {
@@ -1158,7 +2208,7 @@ where
             }
             FuseState::Middle => {
                 self.it.advance();
-                if let None = self.it.get() {
+                if self.it.get().is_none() {
                     self.state = FuseState::End;
                 }
             }
@@ -1436,12 +2486,12 @@ where
 /// A normal, non-streaming, iterator which converts the elements of a streaming iterator into owned
 /// versions.
 ///
-/// Requires the `std` feature.
-#[cfg(feature = "std")]
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
 #[derive(Clone, Debug)]
 pub struct Owned<I>(I);
 
-#[cfg(feature = "std")]
+#[cfg(feature = "alloc")]
 impl<I> Iterator for Owned<I>
 where
     I: StreamingIterator,
@@ -1469,7 +2519,7 @@ where
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(feature = "alloc")]
 impl<I> DoubleEndedIterator for Owned<I>
 where
     I: DoubleEndedStreamingIterator,
@@ -1522,7 +2572,7 @@ where
     {
         if self.n > 0 {
             // nth(n) skips n+1
-            if let None = self.it.nth(self.n - 1) {
+            if self.it.nth(self.n - 1).is_none() {
                 return init;
             }
         }
@@ -1880,6 +2930,31 @@ mod test {
         test(it, &items);
     }
 
+    #[test]
+    fn test_convert_deref() {
+        let items = [&0, &1];
+        let it = convert_deref(items.iter().cloned());
+        test(it, &[0, 1]);
+        let it = convert_deref(items.iter().cloned());
+        test_back(it, &[1, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_convert_deref_owned() {
+        let words = vec!["a".to_owned(), "bc".to_owned(), "def".to_owned()];
+        let it = convert_deref(words.clone());
+        assert_eq!(it.size_hint(), (3, Some(3)));
+        assert_eq!(it.fold(0, |acc, s: &str| acc + s.len()), 6);
+
+        let it = convert_deref(words.clone()).chain(convert_ref(vec!["g"]));
+        assert_eq!(it.rfold(String::new(), |acc, s| acc + s), "gdefbca");
+
+        let bytes = vec![vec![1u8, 2], vec![3]];
+        let mut it = convert_deref(bytes);
+        assert_eq!(it.next(), Some(&[1, 2][..]));
+    }
+
     #[test]
     fn count() {
         let items = [0, 1, 2, 3];
@@ -1980,6 +3055,58 @@ mod test {
         test(it, &[0, 1, 2, 3, 4, 5]);
     }
 
+    #[test]
+    fn flat_map_iter() {
+        let items = [[0, 1, 2], [3, 4, 5]];
+        let mut it = convert(items.iter()).flat_map_iter(|&i| i.iter().cloned());
+        assert_eq!(it.size_hint(), (0, None));
+        assert_eq!(it.next(), Some(&0));
+        assert_eq!(it.size_hint(), (2, None));
+        let it = convert(items.iter()).flat_map_iter(|&i| i.iter().cloned());
+        test(it, &[0, 1, 2, 3, 4, 5]);
+
+        let mut it = convert(items.iter()).flat_map_iter(|&i| i.iter().cloned());
+        it.advance();
+        assert_eq!(it.fold(0, |acc, &i| acc * 10 + i), 12345);
+
+        let lengths = [1, 0, 2];
+        let mut it = convert(lengths.iter()).flat_map_iter(|&&n| 0..n);
+        assert_eq!(it.next(), Some(&0));
+        assert_eq!(it.next(), Some(&0));
+        assert_eq!(it.next(), Some(&1));
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn flatten() {
+        let items = [&[0, 1][..], &[], &[2, 3, 4]];
+        let it = super::flatten(items.iter().map(|i| convert(i.iter().cloned())));
+        assert_eq!(it.size_hint(), (0, None));
+        test(it, &[0, 1, 2, 3, 4]);
+
+        let it = super::flatten(items.iter().map(|i| convert(i.iter().cloned())));
+        test_back(it, &[4, 3, 2, 1, 0]);
+
+        let mut it = super::flatten(items.iter().map(|i| convert(i.iter().cloned())));
+        assert_eq!(it.next(), Some(&0));
+        assert_eq!(it.next_back(), Some(&4));
+        assert_eq!(it.size_hint(), (3, None));
+        assert_eq!(it.next_back(), Some(&3));
+        assert_eq!(it.next(), Some(&1));
+        assert_eq!(it.next(), Some(&2));
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next_back(), None);
+
+        let it = super::flatten(items.iter().map(|i| convert(i.iter().cloned())));
+        assert_eq!(it.fold(0, |acc, &i| acc * 10 + i), 1234);
+        let mut it = super::flatten(items.iter().map(|i| convert(i.iter().cloned())));
+        it.advance_back();
+        assert_eq!(it.rfold(0, |acc, &i| acc * 10 + i), 3210);
+    }
+
     #[test]
     fn nth() {
         let items = [0, 1];
@@ -2073,7 +3200,7 @@ mod test {
         test(it.clone().take_while(|&i| i < 5), &[0, 1, 2, 3]);
     }
 
-    fn _is_object_safe(_: &StreamingIterator<Item = ()>) {}
+    fn _is_object_safe(_: &dyn StreamingIterator<Item = ()>) {}
 
     #[test]
     fn empty_iterator() {
@@ -0,0 +1,155 @@
+//! Macros for looping over streaming iterators.
+
+/// Loops over the elements of a streaming iterator.
+///
+/// `streaming_for!(item in expr => { ... })` evaluates `expr` exactly once, binds the resulting
+/// streaming iterator to a hidden variable, and runs the body with each element bound to `item`.
+/// This avoids the infinite loop of `while let Some(item) = convert(a.iter()).next()`, which
+/// creates a new iterator on every pass. Any pattern may be used in place of `item`, except that
+/// elements cannot be bound by `&mut`, since streaming iterators only lend shared references to
+/// them.
+///
+/// The loop may be labeled, and `break` and `continue` behave as in a `for` loop:
+///
+/// ```
+/// # #[macro_use] extern crate streaming_iterator;
+/// # use streaming_iterator::convert;
+/// # fn main() {
+/// let rows = [[1, 2], [3, 4], [5, 6]];
+/// let mut sum = 0;
+/// streaming_for!('rows: row in convert(rows.iter().cloned()) => {
+///     streaming_for!(&x in convert(row.iter().cloned()) => {
+///         if x == 4 {
+///             continue 'rows;
+///         }
+///         if x == 6 {
+///             break 'rows;
+///         }
+///         sum += x;
+///     });
+/// });
+/// assert_eq!(sum, 1 + 2 + 3 + 5);
+/// # }
+/// ```
+///
+/// Writing `index, item in expr` also binds the position of each element, counting from zero:
+///
+/// ```
+/// # #[macro_use] extern crate streaming_iterator;
+/// # use streaming_iterator::convert;
+/// # fn main() {
+/// let words = ["a", "b", "c"];
+/// streaming_for!(i, &word in convert(words.iter().cloned()) => {
+///     assert_eq!(word, words[i]);
+/// });
+/// # }
+/// ```
+///
+/// # Misuse
+///
+/// Elements borrow from the iterator, so they cannot be kept past the step which produced them:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate streaming_iterator;
+/// # use streaming_iterator::convert;
+/// # fn main() {
+/// let items = [1, 2, 3];
+/// let mut last = None;
+/// streaming_for!(item in convert(items.iter().cloned()) => {
+///     last = Some(item);
+/// });
+/// assert!(last.is_some());
+/// # }
+/// ```
+///
+/// The iterator is consumed by the loop, so it cannot be advanced again afterwards, and it is
+/// not nameable inside the body, so the body cannot advance it behind the loop's back:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate streaming_iterator;
+/// # use streaming_iterator::{convert, StreamingIterator};
+/// # fn main() {
+/// let items = [1, 2, 3];
+/// let mut it = convert(items.iter().cloned());
+/// streaming_for!(_item in it => {});
+/// it.next();
+/// # }
+/// ```
+///
+/// Because the iterator is bound once, the loop always terminates for a finite source, where the
+/// equivalent `while let` loop over a freshly created iterator does not.
+#[macro_export]
+macro_rules! streaming_for {
+    ($label:lifetime: $index:ident, $item:pat in $it:expr => $body:block) => {{
+        let mut it = $it;
+        let mut index = 0usize;
+        $label: while let Some($item) = $crate::StreamingIterator::next(&mut it) {
+            let $index = index;
+            index += 1;
+            $body
+        }
+    }};
+    ($label:lifetime: $item:pat in $it:expr => $body:block) => {{
+        let mut it = $it;
+        $label: while let Some($item) = $crate::StreamingIterator::next(&mut it) $body
+    }};
+    ($index:ident, $item:pat in $it:expr => $body:block) => {{
+        let mut it = $it;
+        let mut index = 0usize;
+        while let Some($item) = $crate::StreamingIterator::next(&mut it) {
+            let $index = index;
+            index += 1;
+            $body
+        }
+    }};
+    ($item:pat in $it:expr => $body:block) => {{
+        let mut it = $it;
+        while let Some($item) = $crate::StreamingIterator::next(&mut it) $body
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn streaming_for() {
+        let items = [0, 1, 2, 3, 4];
+        let mut sum = 0;
+        streaming_for!(&i in convert(items.iter().cloned()) => {
+            if i == 1 {
+                continue;
+            }
+            if i == 4 {
+                break;
+            }
+            sum += i;
+        });
+        assert_eq!(sum, 5);
+
+        let mut sum = 0;
+        streaming_for!(i, item in convert(items.iter().cloned()).skip(1) => {
+            if i == 2 {
+                continue;
+            }
+            assert_eq!(*item, i + 1);
+            sum += i;
+        });
+        assert_eq!(sum, 4);
+
+        // the hidden bindings don't shadow variables of the same name used in the body
+        let (it, index) = (10, 20);
+        let mut count = 0;
+        streaming_for!('outer: n, _ in convert(items.iter()) => {
+            streaming_for!(_ in convert(items.iter()) => {
+                assert_eq!((it, index), (10, 20));
+                if n == 2 {
+                    break 'outer;
+                }
+                count += 1;
+                continue 'outer;
+            });
+        });
+        assert_eq!(count, 2);
+    }
+}
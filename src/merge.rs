@@ -0,0 +1,711 @@
+//! Streaming iterators which merge and join sorted streaming iterators.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cmp::{self, Ordering};
+
+use super::StreamingIterator;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MergeState {
+    Start,
+    A,
+    B,
+    Done,
+}
+
+/// A streaming iterator which merges two sorted streaming iterators.
+#[derive(Debug)]
+pub struct MergeBy<A, B, F> {
+    a: A,
+    b: B,
+    f: F,
+    state: MergeState,
+}
+
+impl<A, B, F> MergeBy<A, B, F> {
+    #[inline]
+    pub(crate) fn new(a: A, b: B, f: F) -> MergeBy<A, B, F> {
+        MergeBy {
+            a,
+            b,
+            f,
+            state: MergeState::Start,
+        }
+    }
+}
+
+impl<A, B, F> StreamingIterator for MergeBy<A, B, F>
+where
+    A: StreamingIterator,
+    B: StreamingIterator<Item = A::Item>,
+    F: FnMut(&A::Item, &A::Item) -> Ordering,
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        match self.state {
+            MergeState::Start => {
+                self.a.advance();
+                self.b.advance();
+            }
+            MergeState::A => self.a.advance(),
+            MergeState::B => self.b.advance(),
+            MergeState::Done => return,
+        }
+
+        self.state = match (self.a.get(), self.b.get()) {
+            (Some(a), Some(b)) => match (self.f)(a, b) {
+                Ordering::Greater => MergeState::B,
+                Ordering::Less | Ordering::Equal => MergeState::A,
+            },
+            (Some(_), None) => MergeState::A,
+            (None, Some(_)) => MergeState::B,
+            (None, None) => MergeState::Done,
+        };
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&A::Item> {
+        match self.state {
+            MergeState::A => self.a.get(),
+            MergeState::B => self.b.get(),
+            MergeState::Start | MergeState::Done => None,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // the item waiting on the side which isn't current hasn't been counted yet
+        let pending = match self.state {
+            MergeState::Start => 0,
+            MergeState::A => self.b.get().is_some() as usize,
+            MergeState::B => self.a.get().is_some() as usize,
+            MergeState::Done => return (0, Some(0)),
+        };
+
+        let (a_lower, a_upper) = self.a.size_hint();
+        let (b_lower, b_upper) = self.b.size_hint();
+
+        let lower = a_lower.saturating_add(b_lower).saturating_add(pending);
+        let upper = match (a_upper, b_upper) {
+            (Some(a), Some(b)) => a.checked_add(b).and_then(|n| n.checked_add(pending)),
+            _ => None,
+        };
+        (lower, upper)
+    }
+}
+
+/// Creates a streaming iterator which merges streaming iterators sorted in ascending order into a
+/// single sorted stream.
+///
+/// Items which compare equal are yielded in the order of the iterators they come from. Items are
+/// never cloned; the iterators are kept in a binary heap ordered by their current elements.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, convert, kmerge};
+/// let merged = kmerge(vec![convert(vec![1, 4, 7]), convert(vec![2, 5]), convert(vec![3, 6])]);
+/// assert_eq!(merged.cloned().collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6, 7]);
+/// ```
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn kmerge<I>(iters: I) -> KMerge<I::Item>
+where
+    I: IntoIterator,
+    I::Item: StreamingIterator,
+    <I::Item as StreamingIterator>::Item: Ord,
+{
+    kmerge_by(iters, Ord::cmp)
+}
+
+/// Creates a streaming iterator which merges streaming iterators sorted according to `f` into a
+/// single sorted stream.
+///
+/// Items which compare equal are yielded in the order of the iterators they come from. Items are
+/// never cloned; the iterators are kept in a binary heap ordered by their current elements.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, convert_ref, kmerge_by};
+/// let a = ["fig", "cherry"];
+/// let b = ["date", "apple", "banana"];
+/// let mut merged = kmerge_by(
+///     vec![convert_ref(a.iter().cloned()), convert_ref(b.iter().cloned())],
+///     |x: &str, y: &str| x.len().cmp(&y.len()),
+/// );
+/// assert_eq!(merged.next(), Some("fig"));
+/// assert_eq!(merged.next(), Some("date"));
+/// assert_eq!(merged.next(), Some("apple"));
+/// assert_eq!(merged.next(), Some("cherry"));
+/// assert_eq!(merged.next(), Some("banana"));
+/// ```
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn kmerge_by<I, F>(iters: I, f: F) -> KMergeBy<I::Item, F>
+where
+    I: IntoIterator,
+    I::Item: StreamingIterator,
+    F: FnMut(
+        &<I::Item as StreamingIterator>::Item,
+        &<I::Item as StreamingIterator>::Item,
+    ) -> Ordering,
+{
+    KMergeBy {
+        heap: iters
+            .into_iter()
+            .enumerate()
+            .map(|(index, it)| Source { it, index })
+            .collect(),
+        f,
+        started: false,
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+struct Source<I> {
+    it: I,
+    index: usize,
+}
+
+/// A streaming iterator which merges any number of streaming iterators sorted in ascending order.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub type KMerge<I> =
+    KMergeBy<I, fn(&<I as StreamingIterator>::Item, &<I as StreamingIterator>::Item) -> Ordering>;
+
+/// A streaming iterator which merges any number of sorted streaming iterators.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct KMergeBy<I, F> {
+    // a binary heap, with the iterator holding the smallest current element first
+    heap: Vec<Source<I>>,
+    f: F,
+    started: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> KMergeBy<I, F>
+where
+    I: StreamingIterator,
+    F: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    fn less(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (&self.heap[a], &self.heap[b]);
+        match (a.it.get(), b.it.get()) {
+            (Some(x), Some(y)) => match (self.f)(x, y) {
+                Ordering::Less => true,
+                Ordering::Greater => false,
+                Ordering::Equal => a.index < b.index,
+            },
+            _ => false,
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let left = 2 * i + 1;
+            let right = left + 1;
+            let mut smallest = i;
+            if left < self.heap.len() && self.less(left, smallest) {
+                smallest = left;
+            }
+            if right < self.heap.len() && self.less(right, smallest) {
+                smallest = right;
+            }
+            if smallest == i {
+                return;
+            }
+            self.heap.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> StreamingIterator for KMergeBy<I, F>
+where
+    I: StreamingIterator,
+    F: FnMut(&I::Item, &I::Item) -> Ordering,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        if !self.started {
+            self.started = true;
+            for source in &mut self.heap {
+                source.it.advance();
+            }
+            self.heap.retain(|source| source.it.get().is_some());
+            for i in (0..self.heap.len() / 2).rev() {
+                self.sift_down(i);
+            }
+        } else if !self.heap.is_empty() {
+            self.heap[0].it.advance();
+            if self.heap[0].it.get().is_none() {
+                self.heap.swap_remove(0);
+            }
+            self.sift_down(0);
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&I::Item> {
+        if self.started {
+            self.heap.first().and_then(|source| source.it.get())
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // every iterator but the first is waiting on an item which hasn't been counted yet
+        let pending = if self.started {
+            self.heap.len().saturating_sub(1)
+        } else {
+            0
+        };
+        self.heap
+            .iter()
+            .fold((pending, Some(pending)), |(lower, upper), source| {
+                let (l, u) = source.it.size_hint();
+                let upper = match (upper, u) {
+                    (Some(a), Some(b)) => a.checked_add(b),
+                    _ => None,
+                };
+                (lower.saturating_add(l), upper)
+            })
+    }
+}
+
+/// A value which may be a left value, a right value, or both.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EitherOrBoth<L, R> {
+    /// Only a left value.
+    Left(L),
+    /// Only a right value.
+    Right(R),
+    /// Both a left and a right value.
+    Both(L, R),
+}
+
+impl<L, R> EitherOrBoth<L, R> {
+    /// Returns the left value, if there is one.
+    #[inline]
+    pub fn left(self) -> Option<L> {
+        match self {
+            EitherOrBoth::Left(l) | EitherOrBoth::Both(l, _) => Some(l),
+            EitherOrBoth::Right(_) => None,
+        }
+    }
+
+    /// Returns the right value, if there is one.
+    #[inline]
+    pub fn right(self) -> Option<R> {
+        match self {
+            EitherOrBoth::Right(r) | EitherOrBoth::Both(_, r) => Some(r),
+            EitherOrBoth::Left(_) => None,
+        }
+    }
+}
+
+/// A difference between two sorted streams reported by `StreamingIterator::diff`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Diff<L, R> {
+    /// A key which is only present in the old stream.
+    Removed(L),
+    /// A key which is only present in the new stream.
+    Added(R),
+    /// A key which is present in both streams with different values.
+    Changed(L, R),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum JoinState {
+    Start,
+    Left,
+    Right,
+    Both,
+    Done,
+}
+
+/// The element yielded by `MergeJoinBy`, borrowing the current items of both iterators.
+#[derive(Debug)]
+pub struct Joined<A, B> {
+    a: A,
+    b: B,
+    state: JoinState,
+}
+
+impl<A, B> Joined<A, B>
+where
+    A: StreamingIterator,
+    B: StreamingIterator,
+{
+    /// Returns the current item of the left iterator, if its key is part of this element.
+    #[inline]
+    pub fn left(&self) -> Option<&A::Item> {
+        match self.state {
+            JoinState::Left | JoinState::Both => self.a.get(),
+            _ => None,
+        }
+    }
+
+    /// Returns the current item of the right iterator, if its key is part of this element.
+    #[inline]
+    pub fn right(&self) -> Option<&B::Item> {
+        match self.state {
+            JoinState::Right | JoinState::Both => self.b.get(),
+            _ => None,
+        }
+    }
+
+    /// Returns the items making up this element.
+    #[inline]
+    pub fn items(&self) -> EitherOrBoth<&A::Item, &B::Item> {
+        match (self.left(), self.right()) {
+            (Some(a), Some(b)) => EitherOrBoth::Both(a, b),
+            (Some(a), None) => EitherOrBoth::Left(a),
+            (None, Some(b)) => EitherOrBoth::Right(b),
+            (None, None) => panic!("no current element"),
+        }
+    }
+}
+
+/// A streaming iterator which joins two streaming iterators sorted by key.
+#[derive(Debug)]
+pub struct MergeJoinBy<A, B, F> {
+    joined: Joined<A, B>,
+    f: F,
+    // whether the current item of the right iterator has been yielded together with a left item
+    matched: bool,
+}
+
+impl<A, B, F> MergeJoinBy<A, B, F> {
+    #[inline]
+    pub(crate) fn new(a: A, b: B, f: F) -> MergeJoinBy<A, B, F> {
+        MergeJoinBy {
+            joined: Joined {
+                a,
+                b,
+                state: JoinState::Start,
+            },
+            f,
+            matched: false,
+        }
+    }
+}
+
+impl<A, B, F> StreamingIterator for MergeJoinBy<A, B, F>
+where
+    A: StreamingIterator,
+    B: StreamingIterator,
+    F: FnMut(&A::Item, &B::Item) -> Ordering,
+{
+    type Item = Joined<A, B>;
+
+    #[inline]
+    fn advance(&mut self) {
+        let joined = &mut self.joined;
+        match joined.state {
+            JoinState::Start => {
+                joined.a.advance();
+                joined.b.advance();
+            }
+            JoinState::Both => {
+                // the right item is kept, since the next left item may have the same key
+                joined.a.advance();
+                self.matched = true;
+            }
+            JoinState::Left => joined.a.advance(),
+            JoinState::Right => {
+                joined.b.advance();
+                self.matched = false;
+            }
+            JoinState::Done => return,
+        }
+
+        loop {
+            joined.state = match (joined.a.get(), joined.b.get()) {
+                (Some(a), Some(b)) => match (self.f)(a, b) {
+                    Ordering::Less => JoinState::Left,
+                    Ordering::Greater => JoinState::Right,
+                    Ordering::Equal => JoinState::Both,
+                },
+                (Some(_), None) => JoinState::Left,
+                (None, Some(_)) => JoinState::Right,
+                (None, None) => JoinState::Done,
+            };
+            if joined.state != JoinState::Right || !self.matched {
+                break;
+            }
+            // the right item has already been joined, so it isn't yielded alone
+            joined.b.advance();
+            self.matched = false;
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&Joined<A, B>> {
+        match self.joined.state {
+            JoinState::Start | JoinState::Done => None,
+            _ => Some(&self.joined),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let joined = &self.joined;
+        // the item waiting on the side which isn't current hasn't been counted yet
+        let (a_pending, b_pending) = match joined.state {
+            JoinState::Start | JoinState::Both => (0, 0),
+            JoinState::Left => (0, (joined.b.get().is_some() && !self.matched) as usize),
+            JoinState::Right => (joined.a.get().is_some() as usize, 0),
+            JoinState::Done => return (0, Some(0)),
+        };
+
+        let (a_lower, a_upper) = joined.a.size_hint();
+        let (b_lower, b_upper) = joined.b.size_hint();
+        let lower = cmp::max(
+            a_lower.saturating_add(a_pending),
+            b_lower.saturating_add(b_pending),
+        );
+        let upper = match (a_upper, b_upper) {
+            (Some(a), Some(b)) => a
+                .checked_add(b)
+                .and_then(|n| n.checked_add(a_pending + b_pending)),
+            _ => None,
+        };
+        (lower, upper)
+    }
+}
+
+/// A streaming iterator which yields the items of a sorted streaming iterator whose keys do not
+/// appear in another.
+#[derive(Debug)]
+pub struct AntiJoin<A, B, F>(MergeJoinBy<A, B, F>);
+
+impl<A, B, F> AntiJoin<A, B, F> {
+    #[inline]
+    pub(crate) fn new(a: A, b: B, f: F) -> AntiJoin<A, B, F> {
+        AntiJoin(MergeJoinBy::new(a, b, f))
+    }
+}
+
+impl<A, B, F> StreamingIterator for AntiJoin<A, B, F>
+where
+    A: StreamingIterator,
+    B: StreamingIterator,
+    F: FnMut(&A::Item, &B::Item) -> Ordering,
+{
+    type Item = A::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        loop {
+            self.0.advance();
+            match self.0.joined.state {
+                JoinState::Left | JoinState::Done => break,
+                _ => {}
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&A::Item> {
+        match self.0.joined.state {
+            JoinState::Left => self.0.joined.a.get(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let joined = &self.0.joined;
+        if joined.state == JoinState::Done {
+            return (0, Some(0));
+        }
+        let pending = (joined.state == JoinState::Right && joined.a.get().is_some()) as usize;
+        (
+            0,
+            joined.a.size_hint().1.and_then(|n| n.checked_add(pending)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn merge_by() {
+        let a = [(1, 'a'), (3, 'a'), (3, 'b'), (5, 'a')];
+        let b = [(0, 'b'), (3, 'c'), (6, 'b')];
+        let mut it =
+            convert(a.iter().cloned()).merge_by(convert(b.iter().cloned()), |x, y| x.0.cmp(&y.0));
+        assert_eq!(it.size_hint(), (7, Some(7)));
+        assert_eq!(it.next(), Some(&(0, 'b')));
+        assert_eq!(it.size_hint(), (6, Some(6)));
+        for item in &[(1, 'a'), (3, 'a'), (3, 'b'), (3, 'c'), (5, 'a'), (6, 'b')] {
+            assert_eq!(it.next(), Some(item));
+        }
+        assert_eq!(it.next(), None);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+
+        let mut it =
+            convert(a.iter().cloned()).merge_by(empty(), |x: &(i32, char), y| x.0.cmp(&y.0));
+        assert_eq!(it.by_ref().count(), 4);
+        it.advance();
+        assert_eq!(it.get(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn kmerge() {
+        let sources = vec![
+            convert(vec![(1, 0), (4, 0), (4, 1)]),
+            convert(vec![]),
+            convert(vec![(2, 2), (4, 2)]),
+            convert(vec![(0, 3), (4, 3), (9, 3)]),
+        ];
+        let mut it = kmerge_by(sources, |x: &(i32, i32), y| x.0.cmp(&y.0));
+        assert_eq!(it.size_hint(), (8, Some(8)));
+        assert_eq!(it.next(), Some(&(0, 3)));
+        assert_eq!(it.size_hint(), (7, Some(7)));
+        assert_eq!(
+            it.cloned().collect::<Vec<_>>(),
+            [(1, 0), (2, 2), (4, 0), (4, 1), (4, 2), (4, 3), (9, 3)]
+        );
+
+        let words = ["b", "d", "a", "c", "e"];
+        let it = super::kmerge(vec![
+            convert_ref(words[..2].iter().cloned()),
+            convert_ref(words[2..].iter().cloned()),
+        ]);
+        assert_eq!(it.owned().collect::<Vec<_>>(), ["a", "b", "c", "d", "e"]);
+
+        let mut it = super::kmerge(Vec::<Empty<u8>>::new());
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn merge_join_by() {
+        let a = [(1, 'a'), (2, 'b'), (4, 'd')];
+        let b = [2, 3, 4, 5];
+        let mut it =
+            convert(a.iter().cloned()).merge_join_by(convert(b.iter().cloned()), |x, y| x.0.cmp(y));
+        assert_eq!(it.size_hint(), (4, Some(7)));
+        let expected = [
+            EitherOrBoth::Left(&(1, 'a')),
+            EitherOrBoth::Both(&(2, 'b'), &2),
+            EitherOrBoth::Right(&3),
+            EitherOrBoth::Both(&(4, 'd'), &4),
+            EitherOrBoth::Right(&5),
+        ];
+        for item in &expected {
+            let joined = it.next().unwrap();
+            assert_eq!(joined.items(), *item);
+            assert_eq!(joined.left(), item.left());
+            assert_eq!(joined.right(), item.right());
+        }
+        assert!(it.next().is_none());
+        assert_eq!(it.size_hint(), (0, Some(0)));
+
+        // left keys may repeat, each joining the same right item; repeated right keys may not
+        let a = [1, 2, 2, 3];
+        let b = [2, 2, 3];
+        let mut it =
+            convert(a.iter().cloned()).merge_join_by(convert(b.iter().cloned()), |x, y| x.cmp(y));
+        let expected = [
+            EitherOrBoth::Left(&1),
+            EitherOrBoth::Both(&2, &2),
+            EitherOrBoth::Both(&2, &2),
+            EitherOrBoth::Right(&2),
+            EitherOrBoth::Both(&3, &3),
+        ];
+        for item in &expected {
+            assert_eq!(it.next().unwrap().items(), *item);
+        }
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn joins() {
+        let a = [1, 2, 4, 6, 7];
+        let b = [(2, 'b'), (3, 'c'), (6, 'f'), (8, 'h')];
+        let it = || convert(a.iter().cloned());
+        let other = || convert(b.iter().cloned());
+
+        let mut pairs = [(0, ' '); 2];
+        let mut n = 0;
+        it().inner_join_for_each(
+            other(),
+            |x, y| x.cmp(&y.0),
+            |x, y| {
+                pairs[n] = (*x, y.1);
+                n += 1;
+            },
+        );
+        assert_eq!(n, 2);
+        assert_eq!(pairs, [(2, 'b'), (6, 'f')]);
+
+        let mut n = 0;
+        convert([2, 2].iter().cloned()).inner_join_for_each(
+            convert([2].iter().cloned()),
+            |x, y| x.cmp(y),
+            |_, _| n += 1,
+        );
+        assert_eq!(n, 2);
+
+        let mut anti = it().anti_join(other(), |x, y| x.cmp(&y.0));
+        for item in &[1, 4, 7] {
+            assert_eq!(anti.next(), Some(item));
+        }
+        assert_eq!(anti.next(), None);
+
+        let mut anti =
+            convert([2, 2, 3].iter().cloned()).anti_join(convert([2].iter().cloned()), Ord::cmp);
+        assert_eq!(anti.next(), Some(&3));
+        assert_eq!(anti.next(), None);
+        assert_eq!(anti.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn diff() {
+        let old = [(1, 'a'), (2, 'b'), (3, 'c'), (5, 'e')];
+        let new = [(2, 'b'), (3, 'x'), (4, 'd'), (5, 'e')];
+        let mut changes = [Diff::Added((0, ' ')); 3];
+        let mut n = 0;
+        convert(old.iter().cloned()).diff(
+            convert(new.iter().cloned()),
+            |x, y| x.0.cmp(&y.0),
+            |x, y| x == y,
+            |change| {
+                changes[n] = match change {
+                    Diff::Removed(&a) => Diff::Removed(a),
+                    Diff::Added(&b) => Diff::Added(b),
+                    Diff::Changed(&a, &b) => Diff::Changed(a, b),
+                };
+                n += 1;
+            },
+        );
+        assert_eq!(n, 3);
+        assert_eq!(
+            changes,
+            [
+                Diff::Removed((1, 'a')),
+                Diff::Changed((3, 'c'), (3, 'x')),
+                Diff::Added((4, 'd')),
+            ]
+        );
+    }
+}
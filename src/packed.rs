@@ -0,0 +1,382 @@
+//! A collection storing unsized elements back to back in a single buffer.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Index, Range};
+
+use super::{
+    convert_ref, ConvertRef, FromStreamingIterator, IntoStreamingIterator, StreamingIterator,
+};
+
+/// Unsized types which can be stored in a `PackedVec`.
+///
+/// Requires the `alloc` feature.
+pub trait PackedItem {
+    /// The buffer holding the contents of every element.
+    type Buf: Default;
+
+    /// Appends `self` to the end of `buf`, returning the new length of `buf`.
+    fn pack(&self, buf: &mut Self::Buf) -> usize;
+
+    /// Returns the element at `range` within `buf`.
+    fn unpack(buf: &Self::Buf, range: Range<usize>) -> &Self;
+
+    /// Removes the contents of `buf`, keeping its allocation.
+    fn clear(buf: &mut Self::Buf);
+}
+
+impl PackedItem for str {
+    type Buf = String;
+
+    #[inline]
+    fn pack(&self, buf: &mut String) -> usize {
+        buf.push_str(self);
+        buf.len()
+    }
+
+    #[inline]
+    fn unpack(buf: &String, range: Range<usize>) -> &str {
+        &buf[range]
+    }
+
+    #[inline]
+    fn clear(buf: &mut String) {
+        buf.clear();
+    }
+}
+
+impl<T> PackedItem for [T]
+where
+    T: Clone,
+{
+    type Buf = Vec<T>;
+
+    #[inline]
+    fn pack(&self, buf: &mut Vec<T>) -> usize {
+        buf.extend_from_slice(self);
+        buf.len()
+    }
+
+    #[inline]
+    fn unpack(buf: &Vec<T>, range: Range<usize>) -> &[T] {
+        &buf[range]
+    }
+
+    #[inline]
+    fn clear(buf: &mut Vec<T>) {
+        buf.clear();
+    }
+}
+
+/// A vector of string or slice elements stored back to back in one buffer.
+///
+/// Collecting a streaming iterator of `str` into a `Vec<String>` allocates once per element, where
+/// a `PackedVec<str>` only grows a single `String` and a table of offsets into it.
+///
+/// Requires the `alloc` feature.
+///
+/// ```
+/// # use streaming_iterator::{convert_ref, StreamingIterator};
+/// let words = ["packed", "into", "one", "buffer"];
+/// let packed = convert_ref(words.iter().cloned()).collect_packed();
+/// assert_eq!(packed.len(), 4);
+/// assert_eq!(&packed[1], "into");
+///
+/// let mut it = packed.iter().rev();
+/// assert_eq!(it.next(), Some("buffer"));
+/// ```
+pub struct PackedVec<T>
+where
+    T: ?Sized + PackedItem,
+{
+    buf: T::Buf,
+    // the end of each element within `buf`
+    ends: Vec<usize>,
+}
+
+impl<T> PackedVec<T>
+where
+    T: ?Sized + PackedItem,
+{
+    /// Creates an empty `PackedVec`.
+    #[inline]
+    pub fn new() -> PackedVec<T> {
+        PackedVec {
+            buf: T::Buf::default(),
+            ends: vec![],
+        }
+    }
+
+    /// Appends a copy of an element to the end of the vector.
+    #[inline]
+    pub fn push(&mut self, item: &T) {
+        let end = item.pack(&mut self.buf);
+        self.ends.push(end);
+    }
+
+    /// Returns the number of elements in the vector.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Returns `true` if the vector holds no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Returns the element at `index`, or `None` if it is out of bounds.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len() {
+            Some(T::unpack(&self.buf, self.range(index)))
+        } else {
+            None
+        }
+    }
+
+    /// Removes every element, keeping the allocated capacity.
+    #[inline]
+    pub fn clear(&mut self) {
+        T::clear(&mut self.buf);
+        self.ends.clear();
+    }
+
+    /// Returns a streaming iterator over the elements of the vector.
+    #[inline]
+    pub fn iter(&self) -> ConvertRef<'_, PackedIter<'_, T>, T> {
+        convert_ref(PackedIter {
+            vec: self,
+            front: 0,
+            back: self.len(),
+        })
+    }
+
+    #[inline]
+    fn range(&self, index: usize) -> Range<usize> {
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        start..self.ends[index]
+    }
+}
+
+impl<T> Clone for PackedVec<T>
+where
+    T: ?Sized + PackedItem,
+    T::Buf: Clone,
+{
+    #[inline]
+    fn clone(&self) -> PackedVec<T> {
+        PackedVec {
+            buf: self.buf.clone(),
+            ends: self.ends.clone(),
+        }
+    }
+}
+
+impl<T> Default for PackedVec<T>
+where
+    T: ?Sized + PackedItem,
+{
+    #[inline]
+    fn default() -> PackedVec<T> {
+        PackedVec::new()
+    }
+}
+
+impl<T> fmt::Debug for PackedVec<T>
+where
+    T: ?Sized + PackedItem + fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut list = fmt.debug_list();
+        self.iter().for_each(|item| {
+            list.entry(&item);
+        });
+        list.finish()
+    }
+}
+
+impl<T> Index<usize> for PackedVec<T>
+where
+    T: ?Sized + PackedItem,
+{
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(item) => item,
+            None => panic!(
+                "index out of bounds: the len is {} but the index is {}",
+                self.len(),
+                index
+            ),
+        }
+    }
+}
+
+impl<T> FromStreamingIterator<T> for PackedVec<T>
+where
+    T: ?Sized + PackedItem,
+{
+    #[inline]
+    fn from_streaming_iter<I>(iter: I) -> PackedVec<T>
+    where
+        I: IntoStreamingIterator<Item = T>,
+    {
+        let it = iter.into_streaming_iter();
+        let mut vec = PackedVec::new();
+        vec.ends.reserve(it.size_hint().0);
+        it.fold(vec, |mut vec, item| {
+            vec.push(item);
+            vec
+        })
+    }
+}
+
+impl<'a, T> IntoStreamingIterator for &'a PackedVec<T>
+where
+    T: ?Sized + PackedItem,
+{
+    type Item = T;
+    type IntoStreamingIter = ConvertRef<'a, PackedIter<'a, T>, T>;
+
+    #[inline]
+    fn into_streaming_iter(self) -> Self::IntoStreamingIter {
+        self.iter()
+    }
+}
+
+/// An iterator over references to the elements of a `PackedVec`.
+///
+/// Requires the `alloc` feature.
+pub struct PackedIter<'a, T>
+where
+    T: ?Sized + PackedItem + 'a,
+{
+    vec: &'a PackedVec<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Clone for PackedIter<'a, T>
+where
+    T: ?Sized + PackedItem,
+{
+    #[inline]
+    fn clone(&self) -> PackedIter<'a, T> {
+        PackedIter {
+            vec: self.vec,
+            front: self.front,
+            back: self.back,
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for PackedIter<'a, T>
+where
+    T: ?Sized + PackedItem,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("PackedIter")
+            .field("front", &self.front)
+            .field("back", &self.back)
+            .finish()
+    }
+}
+
+impl<'a, T> Iterator for PackedIter<'a, T>
+where
+    T: ?Sized + PackedItem,
+{
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        self.vec.get(self.front - 1)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for PackedIter<'a, T>
+where
+    T: ?Sized + PackedItem,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.vec.get(self.back)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for PackedIter<'a, T> where T: ?Sized + PackedItem {}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use super::super::*;
+
+    #[test]
+    fn packed_vec() {
+        let mut packed = PackedVec::<str>::new();
+        assert!(packed.is_empty());
+        packed.push("ab");
+        packed.push("");
+        packed.push("cde");
+        assert_eq!(packed.len(), 3);
+        assert_eq!(&packed[0], "ab");
+        assert_eq!(packed.get(1), Some(""));
+        assert_eq!(packed.get(3), None);
+        assert_eq!(format!("{:?}", packed), r#"["ab", "", "cde"]"#);
+
+        let mut it = packed.iter();
+        assert_eq!(it.size_hint(), (3, Some(3)));
+        assert_eq!(it.next_back(), Some("cde"));
+        assert_eq!(it.next(), Some("ab"));
+        assert_eq!(it.next(), Some(""));
+        assert_eq!(it.next(), None);
+
+        packed.clear();
+        assert!(packed.is_empty());
+        assert_eq!(packed.iter().next(), None);
+        packed.push("f");
+        assert_eq!(&packed[0], "f");
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the len is 1 but the index is 1")]
+    fn index_out_of_bounds() {
+        let mut packed = PackedVec::<[u8]>::new();
+        packed.push(b"a");
+        let _ = &packed[1];
+    }
+
+    #[test]
+    fn collect_packed() {
+        let rows = [&[1, 2][..], &[], &[3]];
+        let packed = convert_ref(rows.iter().cloned()).collect_packed();
+        assert_eq!(packed.len(), 3);
+        assert_eq!(packed.iter().fold(0, |acc, row| acc + row.len()), 3);
+
+        let words = ["a", "bc"];
+        let packed: PackedVec<str> = convert_ref(words.iter().cloned()).collect();
+        let copy: Vec<String> = (&packed).into_streaming_iter().collect();
+        assert_eq!(copy, words);
+    }
+}
@@ -0,0 +1,225 @@
+//! A streaming iterator over borrowed parts of the elements of a streaming iterator.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Index, Range};
+
+use super::StreamingIterator;
+
+/// Returns the range of positions `part` occupies within `whole`, or `None` if it does not lie
+/// within `whole`.
+fn range_of<U>(whole: &[U], part: &[U]) -> Option<Range<usize>> {
+    let size = mem::size_of::<U>();
+    let offset = (part.as_ptr() as usize).checked_sub(whole.as_ptr() as usize);
+    let start = match (offset, size) {
+        // the address of a part of a slice of zero sized values says nothing of its position
+        (_, 0) => 0,
+        (Some(offset), _) => offset / size,
+        (None, _) => return None,
+    };
+    let end = start.checked_add(part.len())?;
+    if end <= whole.len() {
+        Some(start..end)
+    } else {
+        None
+    }
+}
+
+/// A streaming iterator which yields borrowed parts of the elements of a streaming iterator.
+///
+/// Requires the `alloc` feature.
+pub struct FlatMapRef<I, U, F> {
+    it: I,
+    f: F,
+    // the parts of the current element, as ranges of `U`s within it
+    ranges: Vec<Range<usize>>,
+    // the index of the next part to yield
+    next: usize,
+    done: bool,
+    unit: PhantomData<fn() -> U>,
+}
+
+impl<I, U, F> FlatMapRef<I, U, F> {
+    #[inline]
+    pub(crate) fn new(it: I, f: F) -> FlatMapRef<I, U, F> {
+        FlatMapRef {
+            it,
+            f,
+            ranges: vec![],
+            next: 0,
+            done: false,
+            unit: PhantomData,
+        }
+    }
+}
+
+/// Records the ranges of the parts of `item` passed by `f`, skipping any which do not lie within
+/// it.
+#[inline]
+fn split<T, U, F>(item: &T, f: &mut F, ranges: &mut Vec<Range<usize>>)
+where
+    T: ?Sized + AsRef<[U]>,
+    F: for<'a> FnMut(&'a T, &mut dyn FnMut(&'a T)),
+{
+    let whole = item.as_ref();
+    ranges.clear();
+    f(item, &mut |part: &T| {
+        if let Some(range) = range_of(whole, part.as_ref()) {
+            ranges.push(range);
+        }
+    });
+}
+
+impl<I, U, F> fmt::Debug for FlatMapRef<I, U, F>
+where
+    I: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FlatMapRef")
+            .field("it", &self.it)
+            .field("ranges", &self.ranges)
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<I, U, F> StreamingIterator for FlatMapRef<I, U, F>
+where
+    I: StreamingIterator,
+    I::Item: AsRef<[U]> + Index<Range<usize>, Output = I::Item>,
+    F: for<'a> FnMut(&'a I::Item, &mut dyn FnMut(&'a I::Item)),
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        while !self.done {
+            if self.next < self.ranges.len() {
+                self.next += 1;
+                return;
+            }
+
+            self.next = 0;
+            match self.it.next() {
+                Some(item) => split(item, &mut self.f, &mut self.ranges),
+                None => {
+                    self.ranges.clear();
+                    self.done = true;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&I::Item> {
+        if self.next == 0 {
+            return None;
+        }
+        let range = self.ranges[self.next - 1].clone();
+        self.it.get().map(|item| &item[range])
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.ranges.len() - self.next;
+        if self.done || self.it.size_hint().1 == Some(0) {
+            (remaining, Some(remaining))
+        } else {
+            (remaining, None)
+        }
+    }
+
+    #[inline]
+    fn fold<Acc, Fold>(mut self, init: Acc, mut fold: Fold) -> Acc
+    where
+        Self: Sized,
+        Fold: FnMut(Acc, &Self::Item) -> Acc,
+    {
+        let mut acc = init;
+        if self.next > 0 {
+            if let Some(item) = self.it.get() {
+                for range in self.ranges.drain(self.next..) {
+                    acc = fold(acc, &item[range]);
+                }
+            }
+        }
+        if self.done {
+            return acc;
+        }
+
+        let mut f = self.f;
+        let mut ranges = self.ranges;
+        self.it.fold(acc, move |mut acc, item| {
+            split(item, &mut f, &mut ranges);
+            for range in ranges.iter() {
+                acc = fold(acc, &item[range.clone()]);
+            }
+            acc
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::String;
+
+    use super::super::*;
+
+    #[test]
+    fn flat_map_ref() {
+        let lines = ["a,b", "", "c"];
+        let mut it = convert_ref(lines.iter().cloned())
+            .flat_map_ref(|line, emit| line.split(',').filter(|f| !f.is_empty()).for_each(emit));
+        assert_eq!(it.size_hint(), (0, None));
+        assert_eq!(it.next(), Some("a"));
+        assert_eq!(it.size_hint(), (1, None));
+        assert_eq!(it.next(), Some("b"));
+        assert_eq!(it.next(), Some("c"));
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next(), None);
+
+        let mut it = convert_ref(lines.iter().cloned()).flat_map_ref(|line, emit| {
+            for field in line.split(',') {
+                emit(field)
+            }
+        });
+        it.advance();
+        let fields = it.fold(String::new(), |acc, f| acc + "[" + f + "]");
+        assert_eq!(fields, "[b][][c]");
+    }
+
+    #[test]
+    fn chunks() {
+        let rows = [&[1, 2, 3, 4, 5][..], &[6, 7]];
+        let mut it = convert_ref(rows.iter().cloned())
+            .flat_map_ref(|row, emit| row.chunks(2).for_each(emit));
+        assert_eq!(it.next(), Some(&[1, 2][..]));
+        assert_eq!(it.next(), Some(&[3, 4][..]));
+        assert_eq!(it.next(), Some(&[5][..]));
+        assert_eq!(it.next(), Some(&[6, 7][..]));
+        assert_eq!(it.next(), None);
+
+        let units = [(); 4];
+        let mut it =
+            convert_ref(Some(&units[..])).flat_map_ref(|u, emit| u.chunks(3).for_each(emit));
+        assert_eq!(it.next().map(<[()]>::len), Some(3));
+        assert_eq!(it.next().map(<[()]>::len), Some(1));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn foreign_part() {
+        let lines = ["a b"];
+        let mut it = convert_ref(lines.iter().cloned()).flat_map_ref(|line, emit| {
+            emit("x");
+            line.split(' ').for_each(&mut *emit);
+            emit(&"ab"[1..]);
+        });
+        assert_eq!(it.next(), Some("a"));
+        assert_eq!(it.next(), Some("b"));
+        assert_eq!(it.next(), None);
+    }
+}
@@ -0,0 +1,445 @@
+//! Streaming iterators over binary records read from an `io::Read`.
+
+use std::io::{self, Read};
+
+use super::StreamingIterator;
+
+/// Reads from `reader` until `buf` is full or the end of the stream is reached, returning the
+/// number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn truncated(what: &str, expected: usize, found: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!(
+            "truncated {}: expected {} bytes, found {}",
+            what, expected, found
+        ),
+    )
+}
+
+/// Creates a streaming iterator over fixed-size records read from `reader`.
+///
+/// Each record is read into a single buffer of `size` bytes which is reused for every element.
+/// Iteration ends cleanly at the end of the stream; a trailing partial record or an I/O error ends
+/// iteration and is reported by `FixedRecords::error`.
+///
+/// Requires the `std` feature.
+///
+/// # Panics
+///
+/// Panics if `size` is 0.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, fixed_records};
+/// let data: &[u8] = &[1, 2, 3, 4, 5, 6];
+/// let mut records = fixed_records(data, 2);
+/// while let Some(record) = records.next() {
+///     println!("{:?}", record);
+/// }
+/// assert!(records.error().is_none());
+/// ```
+#[inline]
+pub fn fixed_records<R>(reader: R, size: usize) -> FixedRecords<R>
+where
+    R: Read,
+{
+    assert!(size != 0, "record size must be non-zero");
+    FixedRecords {
+        reader,
+        buf: vec![0; size],
+        valid: false,
+        done: false,
+        error: None,
+    }
+}
+
+/// A streaming iterator over fixed-size records read from an `io::Read`.
+///
+/// Requires the `std` feature.
+#[derive(Debug)]
+pub struct FixedRecords<R> {
+    reader: R,
+    buf: Vec<u8>,
+    valid: bool,
+    done: bool,
+    error: Option<io::Error>,
+}
+
+impl<R> FixedRecords<R> {
+    /// Returns the error which ended iteration, if any.
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error which ended iteration, if any, leaving `None` in its place.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Consumes the iterator, returning the underlying reader.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> StreamingIterator for FixedRecords<R>
+where
+    R: Read,
+{
+    type Item = [u8];
+
+    #[inline]
+    fn advance(&mut self) {
+        self.valid = false;
+        if self.done {
+            return;
+        }
+
+        match read_full(&mut self.reader, &mut self.buf) {
+            Ok(n) if n == self.buf.len() => self.valid = true,
+            Ok(0) => self.done = true,
+            Ok(n) => {
+                self.done = true;
+                self.error = Some(truncated("record", self.buf.len(), n));
+            }
+            Err(e) => {
+                self.done = true;
+                self.error = Some(e);
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[u8]> {
+        if self.valid {
+            Some(&self.buf)
+        } else {
+            None
+        }
+    }
+}
+
+/// The encoding of the length prefix of each frame read by `framed_records`.
+///
+/// Requires the `std` feature.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LengthPrefix {
+    /// A big endian `u16`.
+    U16Be,
+    /// A little endian `u16`.
+    U16Le,
+    /// A big endian `u32`.
+    U32Be,
+    /// A little endian `u32`.
+    U32Le,
+    /// An unsigned LEB128 varint.
+    Leb128,
+}
+
+/// Creates a streaming iterator over length-prefixed frames read from `reader`.
+///
+/// Each frame consists of a length encoded as described by `prefix`, followed by that many bytes of
+/// payload. Payloads are read into a single buffer which is reused for every element. Iteration
+/// ends cleanly at the end of the stream; a truncated frame, a frame longer than `max_len` bytes or
+/// an I/O error ends iteration and is reported by `FramedRecords::error`.
+///
+/// Requires the `std` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, framed_records, LengthPrefix};
+/// let data: &[u8] = &[0, 2, b'h', b'i', 0, 1, b'!'];
+/// let mut frames = framed_records(data, LengthPrefix::U16Be, 1024);
+/// assert_eq!(frames.next(), Some(&b"hi"[..]));
+/// assert_eq!(frames.next(), Some(&b"!"[..]));
+/// assert_eq!(frames.next(), None);
+/// assert!(frames.error().is_none());
+/// ```
+#[inline]
+pub fn framed_records<R>(reader: R, prefix: LengthPrefix, max_len: usize) -> FramedRecords<R>
+where
+    R: Read,
+{
+    FramedRecords {
+        reader,
+        prefix,
+        max_len,
+        buf: vec![],
+        valid: false,
+        done: false,
+        error: None,
+    }
+}
+
+/// A streaming iterator over length-prefixed frames read from an `io::Read`.
+///
+/// Requires the `std` feature.
+#[derive(Debug)]
+pub struct FramedRecords<R> {
+    reader: R,
+    prefix: LengthPrefix,
+    max_len: usize,
+    buf: Vec<u8>,
+    valid: bool,
+    done: bool,
+    error: Option<io::Error>,
+}
+
+impl<R> FramedRecords<R> {
+    /// Returns the error which ended iteration, if any.
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error which ended iteration, if any, leaving `None` in its place.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Consumes the iterator, returning the underlying reader.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> FramedRecords<R>
+where
+    R: Read,
+{
+    /// Reads the next length prefix, returning `None` at a clean end of stream.
+    fn read_len(&mut self) -> io::Result<Option<u64>> {
+        let width = match self.prefix {
+            LengthPrefix::U16Be | LengthPrefix::U16Le => 2,
+            LengthPrefix::U32Be | LengthPrefix::U32Le => 4,
+            LengthPrefix::Leb128 => return self.read_leb128(),
+        };
+
+        let mut bytes = [0; 4];
+        match read_full(&mut self.reader, &mut bytes[..width])? {
+            0 => return Ok(None),
+            n if n < width => return Err(truncated("frame header", width, n)),
+            _ => {}
+        }
+
+        let len = match self.prefix {
+            LengthPrefix::U16Be => u64::from(u16::from_be_bytes([bytes[0], bytes[1]])),
+            LengthPrefix::U16Le => u64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+            LengthPrefix::U32Be => u64::from(u32::from_be_bytes(bytes)),
+            LengthPrefix::U32Le => u64::from(u32::from_le_bytes(bytes)),
+            LengthPrefix::Leb128 => unreachable!(),
+        };
+        Ok(Some(len))
+    }
+
+    fn read_leb128(&mut self) -> io::Result<Option<u64>> {
+        let mut len = 0u64;
+        let mut shift = 0;
+        loop {
+            let mut byte = [0];
+            if read_full(&mut self.reader, &mut byte)? == 0 {
+                if shift == 0 {
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated frame header: unterminated LEB128 length",
+                ));
+            }
+
+            let bits = u64::from(byte[0] & 0x7f);
+            if shift >= 64 || (bits << shift) >> shift != bits {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "LEB128 frame length overflows a u64",
+                ));
+            }
+            len |= bits << shift;
+            shift += 7;
+
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(len));
+            }
+        }
+    }
+
+    fn read_frame(&mut self) -> io::Result<bool> {
+        let len = match self.read_len()? {
+            Some(len) => len,
+            None => return Ok(false),
+        };
+
+        if len > self.max_len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame length {} exceeds maximum of {}", len, self.max_len),
+            ));
+        }
+
+        let len = len as usize;
+        self.buf.resize(len, 0);
+        let n = read_full(&mut self.reader, &mut self.buf)?;
+        if n < len {
+            return Err(truncated("frame", len, n));
+        }
+        Ok(true)
+    }
+}
+
+impl<R> StreamingIterator for FramedRecords<R>
+where
+    R: Read,
+{
+    type Item = [u8];
+
+    #[inline]
+    fn advance(&mut self) {
+        self.valid = false;
+        if self.done {
+            return;
+        }
+
+        match self.read_frame() {
+            Ok(true) => self.valid = true,
+            Ok(false) => self.done = true,
+            Err(e) => {
+                self.done = true;
+                self.error = Some(e);
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[u8]> {
+        if self.valid {
+            Some(&self.buf)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::*;
+
+    fn collect<I>(mut it: I) -> Vec<Vec<u8>>
+    where
+        I: StreamingIterator<Item = [u8]>,
+    {
+        let mut records = vec![];
+        while let Some(record) = it.next() {
+            records.push(record.to_vec());
+        }
+        records
+    }
+
+    #[test]
+    fn fixed() {
+        let data: &[u8] = &[1, 2, 3, 4, 5, 6];
+        let mut it = fixed_records(data, 3);
+        assert_eq!(collect(&mut it), [[1, 2, 3], [4, 5, 6]]);
+        assert!(it.error().is_none());
+        it.advance();
+        assert_eq!(it.get(), None);
+    }
+
+    #[test]
+    fn fixed_truncated() {
+        let data: &[u8] = &[1, 2, 3, 4, 5];
+        let mut it = fixed_records(data, 3);
+        assert_eq!(collect(&mut it), [[1, 2, 3]]);
+        assert_eq!(
+            it.take_error().map(|e| e.kind()),
+            Some(io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn framed() {
+        let cases: &[(LengthPrefix, &[u8])] = &[
+            (LengthPrefix::U16Be, &[0, 2, 1, 2, 0, 0, 0, 1, 3]),
+            (LengthPrefix::U16Le, &[2, 0, 1, 2, 0, 0, 1, 0, 3]),
+            (
+                LengthPrefix::U32Be,
+                &[0, 0, 0, 2, 1, 2, 0, 0, 0, 0, 0, 0, 0, 1, 3],
+            ),
+            (
+                LengthPrefix::U32Le,
+                &[2, 0, 0, 0, 1, 2, 0, 0, 0, 0, 1, 0, 0, 0, 3],
+            ),
+            (LengthPrefix::Leb128, &[2, 1, 2, 0, 1, 3]),
+        ];
+
+        for &(prefix, data) in cases {
+            let mut it = framed_records(data, prefix, 16);
+            assert_eq!(collect(&mut it), [&[1, 2][..], &[], &[3]], "{:?}", prefix);
+            assert!(it.error().is_none());
+        }
+    }
+
+    #[test]
+    fn framed_leb128_multibyte() {
+        let mut data = vec![0x80 | 0x2c, 0x02];
+        data.extend((0..300).map(|i| i as u8));
+        let mut it = framed_records(&data[..], LengthPrefix::Leb128, 300);
+        assert_eq!(it.next().map(<[u8]>::len), Some(300));
+        assert_eq!(it.next(), None);
+        assert!(it.error().is_none());
+
+        let data: &[u8] = &[0xff; 11];
+        let mut it = framed_records(data, LengthPrefix::Leb128, 300);
+        assert_eq!(it.next(), None);
+        assert_eq!(
+            it.error().map(io::Error::kind),
+            Some(io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn framed_errors() {
+        let data: &[u8] = &[0, 1, 7, 0, 3, 1, 2];
+        let mut it = framed_records(data, LengthPrefix::U16Be, 16);
+        assert_eq!(collect(&mut it), [[7]]);
+        assert_eq!(
+            it.error().map(io::Error::kind),
+            Some(io::ErrorKind::UnexpectedEof)
+        );
+
+        let data: &[u8] = &[0, 1, 7, 0];
+        let mut it = framed_records(data, LengthPrefix::U16Be, 16);
+        assert_eq!(collect(&mut it), [[7]]);
+        assert_eq!(
+            it.error().map(io::Error::kind),
+            Some(io::ErrorKind::UnexpectedEof)
+        );
+
+        let data: &[u8] = &[0, 1, 7, 0, 17];
+        let mut it = framed_records(data, LengthPrefix::U16Be, 16);
+        assert_eq!(collect(&mut it), [[7]]);
+        assert_eq!(
+            it.error().map(io::Error::kind),
+            Some(io::ErrorKind::InvalidData)
+        );
+        it.advance();
+        assert_eq!(it.get(), None);
+    }
+}
@@ -0,0 +1,391 @@
+//! Streaming iterators which keep copies of elements for later consumers.
+
+use alloc::borrow::ToOwned;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cell::RefCell;
+use core::fmt;
+
+use super::{ConvertRef, PackedItem, PackedIter, PackedVec, StreamingIterator};
+
+/// A streaming iterator which records a copy of each element of a streaming iterator as it passes
+/// through.
+///
+/// Requires the `alloc` feature.
+pub struct Recorded<I>
+where
+    I: StreamingIterator,
+    I::Item: PackedItem,
+{
+    it: I,
+    recording: PackedVec<I::Item>,
+}
+
+impl<I> Recorded<I>
+where
+    I: StreamingIterator,
+    I::Item: PackedItem,
+{
+    #[inline]
+    pub(crate) fn new(it: I) -> Recorded<I> {
+        Recorded {
+            it,
+            recording: PackedVec::new(),
+        }
+    }
+
+    /// Returns a streaming iterator over the elements recorded so far.
+    ///
+    /// This can be called any number of times, each returning a fresh iterator from the first
+    /// recorded element.
+    #[inline]
+    pub fn replay(&self) -> ConvertRef<'_, PackedIter<'_, I::Item>, I::Item> {
+        self.recording.iter()
+    }
+
+    /// Returns the elements recorded so far.
+    #[inline]
+    pub fn recording(&self) -> &PackedVec<I::Item> {
+        &self.recording
+    }
+
+    /// Consumes the iterator, returning the elements recorded so far.
+    #[inline]
+    pub fn into_recording(self) -> PackedVec<I::Item> {
+        self.recording
+    }
+}
+
+impl<I> fmt::Debug for Recorded<I>
+where
+    I: StreamingIterator + fmt::Debug,
+    I::Item: PackedItem + fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Recorded")
+            .field("it", &self.it)
+            .field("recording", &self.recording)
+            .finish()
+    }
+}
+
+impl<I> StreamingIterator for Recorded<I>
+where
+    I: StreamingIterator,
+    I::Item: PackedItem,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn advance(&mut self) {
+        if let Some(item) = self.it.next() {
+            self.recording.push(item);
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&I::Item> {
+        self.it.get()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+}
+
+struct TeeState<I>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    it: I,
+    done: bool,
+    capacity: usize,
+    // elements pulled by one consumer which the other has yet to reach
+    buf: VecDeque<<I::Item as ToOwned>::Owned>,
+    // the number of elements pulled from `it` before the front of `buf`
+    base: usize,
+    spare: Vec<<I::Item as ToOwned>::Owned>,
+    // the number of elements yielded to each consumer, or `None` once it has been dropped
+    positions: [Option<usize>; 2],
+}
+
+/// One of the two streaming iterators returned by `StreamingIterator::tee`.
+///
+/// Requires the `alloc` feature.
+pub struct Tee<I>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    state: Rc<RefCell<TeeState<I>>>,
+    side: usize,
+    current: Option<<I::Item as ToOwned>::Owned>,
+    valid: bool,
+}
+
+impl<I> Tee<I>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    pub(crate) fn new(it: I, capacity: usize) -> (Tee<I>, Tee<I>) {
+        let state = Rc::new(RefCell::new(TeeState {
+            it,
+            done: false,
+            capacity,
+            buf: VecDeque::new(),
+            base: 0,
+            spare: vec![],
+            positions: [Some(0), Some(0)],
+        }));
+        let tee = |side| Tee {
+            state: state.clone(),
+            side,
+            current: None,
+            valid: false,
+        };
+        (tee(0), tee(1))
+    }
+
+    /// Returns the number of elements this iterator is behind the other one.
+    ///
+    /// These elements are held in the shared buffer until this iterator reaches them.
+    #[inline]
+    pub fn lag(&self) -> usize {
+        let state = (*self.state).borrow();
+        match (state.positions[self.side], state.positions[1 - self.side]) {
+            (Some(ours), Some(theirs)) => theirs.saturating_sub(ours),
+            _ => 0,
+        }
+    }
+
+    /// Advances the iterator unless that would exceed the capacity of the shared buffer, returning
+    /// whether it was advanced.
+    ///
+    /// This fails when the other iterator is `capacity` elements behind and this one would have to
+    /// pull a new element, even if the underlying iterator has none left. Nothing is pulled in that
+    /// case, and the current element remains in place.
+    pub fn try_advance(&mut self) -> bool {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        let pos = state.positions[self.side].unwrap();
+        let other = state.positions[1 - self.side];
+
+        if pos < state.base + state.buf.len() {
+            // the other iterator is ahead and has already pulled this element; since this one is
+            // the last to need it, move it out of the buffer
+            let item = state.buf.pop_front().unwrap();
+            state.base += 1;
+            if let Some(old) = self.current.replace(item) {
+                state.spare.push(old);
+            }
+            self.valid = true;
+        } else if state.done {
+            self.valid = false;
+            return true;
+        } else {
+            let needed = match other {
+                Some(other) => other <= pos,
+                None => false,
+            };
+            if needed && state.buf.len() == state.capacity {
+                return false;
+            }
+            match state.it.next() {
+                Some(item) => {
+                    self.current = Some(copy(item, self.current.take()));
+                    self.valid = true;
+                    if needed {
+                        let spare = state.spare.pop();
+                        state.buf.push_back(copy(item, spare));
+                    } else {
+                        state.base += 1;
+                    }
+                }
+                None => {
+                    state.done = true;
+                    self.valid = false;
+                    return true;
+                }
+            }
+        }
+        state.positions[self.side] = Some(pos + 1);
+        true
+    }
+}
+
+impl<I> fmt::Debug for Tee<I>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Tee")
+            .field("side", &self.side)
+            .field("lag", &self.lag())
+            .field("valid", &self.valid)
+            .finish()
+    }
+}
+
+impl<I> StreamingIterator for Tee<I>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    type Item = I::Item;
+
+    fn advance(&mut self) {
+        if !self.try_advance() {
+            panic!(
+                "tee buffer capacity of {} exceeded; one iterator fell too far behind the other",
+                (*self.state).borrow().capacity
+            );
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&I::Item> {
+        if self.valid {
+            self.current.as_ref().map(Borrow::borrow)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let state = (*self.state).borrow();
+        let pos = state.positions[self.side].unwrap();
+        let buffered = (state.base + state.buf.len()).saturating_sub(pos);
+        if state.done {
+            return (buffered, Some(buffered));
+        }
+        let (lower, upper) = state.it.size_hint();
+        (
+            lower.saturating_add(buffered),
+            upper.and_then(|upper| upper.checked_add(buffered)),
+        )
+    }
+}
+
+impl<I> Drop for Tee<I>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.positions[self.side] = None;
+        if state.positions[1 - self.side].is_none() {
+            return;
+        }
+        // the other iterator is now the only one, so nothing it pulls needs buffering, and what it
+        // has already pulled was only kept for this one
+        let pulled = state.positions[1 - self.side].unwrap();
+        while state.base < pulled {
+            state.buf.pop_front();
+            state.base += 1;
+        }
+    }
+}
+
+fn copy<T, O>(item: &T, buf: Option<O>) -> O
+where
+    T: ?Sized + ToOwned<Owned = O>,
+{
+    match buf {
+        Some(mut buf) => {
+            item.clone_into(&mut buf);
+            buf
+        }
+        None => item.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::String;
+
+    use super::super::*;
+
+    #[test]
+    fn record() {
+        let words = ["a", "bc", "d"];
+        let mut it = convert_ref(words.iter().cloned()).record();
+        assert_eq!(it.next(), Some("a"));
+        assert_eq!(it.replay().count(), 1);
+        assert_eq!(it.by_ref().fold(0, |acc, w| acc + w.len()), 3);
+
+        let mut replay = it.replay();
+        assert_eq!(replay.next(), Some("a"));
+        assert_eq!(replay.next_back(), Some("d"));
+        assert_eq!(it.replay().count(), 3);
+        assert_eq!(it.into_recording().len(), 3);
+
+        let rows = [&[1, 2][..], &[3]];
+        let mut it = convert_ref(rows.iter().cloned()).record();
+        while it.next().is_some() {}
+        assert_eq!(&it.recording()[0], &[1, 2][..]);
+    }
+
+    #[test]
+    fn tee() {
+        let items = [0, 1, 2, 3];
+        let (mut a, mut b) = convert(items.iter().cloned()).tee(2);
+        assert_eq!(a.size_hint(), (4, Some(4)));
+        assert_eq!(a.next(), Some(&0));
+        assert_eq!(a.next(), Some(&1));
+        assert_eq!((a.lag(), b.lag()), (0, 2));
+        assert_eq!(b.size_hint(), (4, Some(4)));
+        assert_eq!(b.next(), Some(&0));
+        assert_eq!(b.next(), Some(&1));
+        assert_eq!(b.next(), Some(&2));
+        assert_eq!((a.lag(), b.lag()), (1, 0));
+        assert_eq!(a.next(), Some(&2));
+        drop(b);
+        assert_eq!(a.lag(), 0);
+        assert_eq!(a.next(), Some(&3));
+        assert_eq!(a.next(), None);
+        assert_eq!(a.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn tee_str() {
+        let words = ["ab", "c"];
+        let (a, mut b) = convert_ref(words.iter().cloned()).tee(2);
+        assert_eq!(b.next(), Some("ab"));
+        let joined = a.fold(String::new(), |acc, w| acc + w);
+        assert_eq!(joined, "abc");
+        assert_eq!(b.next(), Some("c"));
+        assert_eq!(b.next(), None);
+    }
+
+    #[test]
+    fn tee_try_advance() {
+        let items = [0, 1, 2];
+        let (mut a, mut b) = convert(items.iter().cloned()).tee(1);
+        assert!(a.try_advance());
+        assert!(!a.try_advance());
+        assert_eq!(a.get(), Some(&0));
+        assert_eq!(b.next(), Some(&0));
+        assert!(a.try_advance());
+        assert_eq!(a.get(), Some(&1));
+        assert_eq!((a.lag(), b.lag()), (0, 1));
+        assert_eq!(b.next(), Some(&1));
+        assert_eq!(b.next(), Some(&2));
+        assert_eq!(a.next(), Some(&2));
+    }
+
+    #[test]
+    #[should_panic(expected = "tee buffer capacity of 1 exceeded")]
+    fn tee_capacity() {
+        let items = [0, 1, 2];
+        let (mut a, _b) = convert(items.iter().cloned()).tee(1);
+        a.advance();
+        a.advance();
+    }
+}
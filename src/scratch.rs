@@ -0,0 +1,478 @@
+//! Streaming iterators which render each element into a reused buffer.
+
+#[cfg(feature = "alloc")]
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::io;
+
+use super::{DoubleEndedStreamingIterator, StreamingIterator};
+
+/// Buffers which can be emptied while keeping their allocation, as used by
+/// `StreamingIterator::map_into`.
+pub trait Clear {
+    /// Removes the contents of the buffer.
+    fn clear(&mut self);
+}
+
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl Clear for String {
+    #[inline]
+    fn clear(&mut self) {
+        String::clear(self)
+    }
+}
+
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> Clear for Vec<T> {
+    #[inline]
+    fn clear(&mut self) {
+        Vec::clear(self)
+    }
+}
+
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> Clear for VecDeque<T> {
+    #[inline]
+    fn clear(&mut self) {
+        VecDeque::clear(self)
+    }
+}
+
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<K, V> Clear for BTreeMap<K, V> {
+    #[inline]
+    fn clear(&mut self) {
+        BTreeMap::clear(self)
+    }
+}
+
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> Clear for BTreeSet<T> {
+    #[inline]
+    fn clear(&mut self) {
+        BTreeSet::clear(self)
+    }
+}
+
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+impl<K, V, S> Clear for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    #[inline]
+    fn clear(&mut self) {
+        HashMap::clear(self)
+    }
+}
+
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+impl<T, S> Clear for HashSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    #[inline]
+    fn clear(&mut self) {
+        HashSet::clear(self)
+    }
+}
+
+/// A streaming iterator which renders the elements of a streaming iterator into a reused buffer.
+#[derive(Debug)]
+pub struct MapInto<I, B, F> {
+    it: I,
+    buf: B,
+    f: F,
+    valid: bool,
+}
+
+impl<I, B, F> MapInto<I, B, F> {
+    #[inline]
+    pub(crate) fn new(it: I, buf: B, f: F) -> MapInto<I, B, F> {
+        MapInto {
+            it,
+            buf,
+            f,
+            valid: false,
+        }
+    }
+
+    /// Consumes the iterator, returning the buffer.
+    #[inline]
+    pub fn into_buf(self) -> B {
+        self.buf
+    }
+}
+
+impl<I, B, F> StreamingIterator for MapInto<I, B, F>
+where
+    I: StreamingIterator,
+    B: Clear,
+    F: FnMut(&I::Item, &mut B),
+{
+    type Item = B;
+
+    #[inline]
+    fn advance(&mut self) {
+        self.valid = match self.it.next() {
+            Some(item) => {
+                self.buf.clear();
+                (self.f)(item, &mut self.buf);
+                true
+            }
+            None => false,
+        };
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&B> {
+        if self.valid {
+            Some(&self.buf)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+
+    #[inline]
+    fn fold<Acc, Fold>(self, init: Acc, mut fold: Fold) -> Acc
+    where
+        Self: Sized,
+        Fold: FnMut(Acc, &B) -> Acc,
+    {
+        let mut buf = self.buf;
+        let mut f = self.f;
+        self.it.fold(init, move |acc, item| {
+            buf.clear();
+            f(item, &mut buf);
+            fold(acc, &buf)
+        })
+    }
+}
+
+impl<I, B, F> DoubleEndedStreamingIterator for MapInto<I, B, F>
+where
+    I: DoubleEndedStreamingIterator,
+    B: Clear,
+    F: FnMut(&I::Item, &mut B),
+{
+    #[inline]
+    fn advance_back(&mut self) {
+        self.valid = match self.it.next_back() {
+            Some(item) => {
+                self.buf.clear();
+                (self.f)(item, &mut self.buf);
+                true
+            }
+            None => false,
+        };
+    }
+
+    #[inline]
+    fn rfold<Acc, Fold>(self, init: Acc, mut fold: Fold) -> Acc
+    where
+        Self: Sized,
+        Fold: FnMut(Acc, &B) -> Acc,
+    {
+        let mut buf = self.buf;
+        let mut f = self.f;
+        self.it.rfold(init, move |acc, item| {
+            buf.clear();
+            f(item, &mut buf);
+            fold(acc, &buf)
+        })
+    }
+}
+
+/// A streaming iterator which formats the elements of a streaming iterator into a reused `String`.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct MapFmt<I, F> {
+    it: I,
+    buf: String,
+    f: F,
+    valid: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> MapFmt<I, F> {
+    #[inline]
+    pub(crate) fn new(it: I, f: F) -> MapFmt<I, F> {
+        MapFmt {
+            it,
+            buf: String::new(),
+            f,
+            valid: false,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> MapFmt<I, F>
+where
+    I: StreamingIterator,
+    F: FnMut(&I::Item, &mut String) -> fmt::Result,
+{
+    #[inline]
+    fn render(&mut self, found: bool) {
+        self.buf.clear();
+        if found {
+            (self.f)(self.it.get().unwrap(), &mut self.buf)
+                .expect("a formatting trait implementation returned an error");
+        }
+        self.valid = found;
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> StreamingIterator for MapFmt<I, F>
+where
+    I: StreamingIterator,
+    F: FnMut(&I::Item, &mut String) -> fmt::Result,
+{
+    type Item = str;
+
+    #[inline]
+    fn advance(&mut self) {
+        let found = self.it.next().is_some();
+        self.render(found);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&str> {
+        if self.valid {
+            Some(&self.buf)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, F> DoubleEndedStreamingIterator for MapFmt<I, F>
+where
+    I: DoubleEndedStreamingIterator,
+    F: FnMut(&I::Item, &mut String) -> fmt::Result,
+{
+    #[inline]
+    fn advance_back(&mut self) {
+        let found = self.it.next_back().is_some();
+        self.render(found);
+    }
+}
+
+/// A streaming iterator which writes the elements of a streaming iterator into a reused byte
+/// buffer.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct MapBytes<I, F> {
+    it: I,
+    buf: Vec<u8>,
+    f: F,
+    valid: bool,
+    done: bool,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<I, F> MapBytes<I, F> {
+    #[inline]
+    pub(crate) fn new(it: I, f: F) -> MapBytes<I, F> {
+        MapBytes {
+            it,
+            buf: vec![],
+            f,
+            valid: false,
+            done: false,
+            error: None,
+        }
+    }
+
+    /// Returns the error which ended iteration, if any.
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error which ended iteration, if any, leaving `None` in its place.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I, F> MapBytes<I, F>
+where
+    I: StreamingIterator,
+    F: FnMut(&I::Item, &mut Vec<u8>) -> io::Result<()>,
+{
+    #[inline]
+    fn write(&mut self, found: bool) {
+        self.valid = false;
+        if !found {
+            self.done = true;
+            return;
+        }
+
+        self.buf.clear();
+        match (self.f)(self.it.get().unwrap(), &mut self.buf) {
+            Ok(()) => self.valid = true,
+            Err(e) => {
+                self.done = true;
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I, F> StreamingIterator for MapBytes<I, F>
+where
+    I: StreamingIterator,
+    F: FnMut(&I::Item, &mut Vec<u8>) -> io::Result<()>,
+{
+    type Item = [u8];
+
+    #[inline]
+    fn advance(&mut self) {
+        if self.done {
+            self.valid = false;
+            return;
+        }
+        let found = self.it.next().is_some();
+        self.write(found);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&[u8]> {
+        if self.valid {
+            Some(&self.buf)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            self.it.size_hint()
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I, F> DoubleEndedStreamingIterator for MapBytes<I, F>
+where
+    I: DoubleEndedStreamingIterator,
+    F: FnMut(&I::Item, &mut Vec<u8>) -> io::Result<()>,
+{
+    #[inline]
+    fn advance_back(&mut self) {
+        if self.done {
+            self.valid = false;
+            return;
+        }
+        let found = self.it.next_back().is_some();
+        self.write(found);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "alloc")]
+    use super::super::*;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn map_into() {
+        let items = [1, 2, 3];
+        let mut it = convert(items.iter().cloned()).map_into(vec![], |&i, buf| {
+            buf.push(i);
+            buf.push(i * i);
+        });
+        assert_eq!(it.size_hint(), (3, Some(3)));
+        assert_eq!(it.next().map(|b| &b[..]), Some(&[1, 1][..]));
+        assert_eq!(it.next_back().map(|b| &b[..]), Some(&[3, 9][..]));
+        assert_eq!(it.next().map(|b| &b[..]), Some(&[2, 4][..]));
+        assert_eq!(it.next(), None);
+
+        let it = convert(items.iter().cloned()).map_into(vec![], |&i, buf| buf.push(i * 10));
+        assert_eq!(it.fold(0, |acc, buf| acc + buf.iter().sum::<i32>()), 60);
+        let it = convert(items.iter().cloned()).map_into(vec![], |&i, buf| buf.push(i * 10));
+        assert_eq!(it.rfold(0, |acc, buf| acc * 2 + buf[buf.len() - 1]), 170);
+
+        // the buffer is cleared before each element, so only its allocation is kept
+        let mut it = convert(items.iter().cloned()).map_into(vec![0; 8], |&i, buf| buf.push(i));
+        assert_eq!(it.next().map(|b| &b[..]), Some(&[1][..]));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn map_fmt() {
+        use std::fmt::Write;
+
+        let items = [1.5, 2.25];
+        let mut it = convert(items.iter().cloned()).map_fmt(|i, buf| write!(buf, "{:.1}", i));
+        assert_eq!(it.next(), Some("1.5"));
+        assert_eq!(it.next(), Some("2.2"));
+        assert_eq!(it.next(), None);
+
+        let mut it = convert(items.iter().cloned()).map_fmt(|i, buf| write!(buf, "<{}>", i));
+        assert_eq!(it.next_back(), Some("<2.25>"));
+        assert_eq!(it.next_back(), Some("<1.5>"));
+        assert_eq!(it.next_back(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn map_bytes() {
+        use std::io::{self, Write};
+
+        let items = [1u16, 258, 3];
+        let mut it = convert(items.iter().cloned()).map_bytes(|&i, buf| {
+            if i == 3 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "three"));
+            }
+            buf.write_all(&i.to_be_bytes())
+        });
+        assert_eq!(it.next(), Some(&[0, 1][..]));
+        assert_eq!(it.next_back(), None);
+        assert_eq!(it.error().map(|e| e.to_string()), Some("three".to_owned()));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+    }
+}
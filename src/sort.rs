@@ -0,0 +1,485 @@
+//! Sorting of streams which may not fit in memory.
+
+use std::cmp::{self, Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::vec;
+
+use super::{framed_records, FramedRecords, LengthPrefix, StreamingIterator};
+
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+// the maximum number of run files read at once
+const FAN_IN: usize = 16;
+
+fn create_run(tmp_dir: &Path) -> io::Result<(PathBuf, File)> {
+    let path = tmp_dir.join(format!(
+        "streaming-iterator-sort-{}-{}.run",
+        process::id(),
+        NEXT_RUN.fetch_add(1, AtomicOrdering::Relaxed)
+    ));
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    Ok((path, file))
+}
+
+fn open_run(path: &Path) -> io::Result<FramedRecords<BufReader<File>>> {
+    let file = File::open(path)?;
+    Ok(framed_records(
+        BufReader::new(file),
+        LengthPrefix::Leb128,
+        usize::MAX,
+    ))
+}
+
+fn write_len<W: Write>(w: &mut W, mut len: usize) -> io::Result<()> {
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+enum Source<T> {
+    File(FramedRecords<BufReader<File>>),
+    Memory(vec::IntoIter<T>),
+}
+
+struct Run<T> {
+    source: Source<T>,
+    item: Option<T>,
+    // the number of items in the run which have yet to be read into `item`
+    unread: usize,
+}
+
+/// A streaming iterator over items sorted by `StreamingIterator::sort_external`.
+///
+/// Temporary files holding sorted runs are removed when the iterator is dropped.
+///
+/// Requires the `std` feature.
+pub struct ExternalSort<T, D> {
+    runs: Vec<Run<T>>,
+    // a binary heap of indices into `runs`, with the run holding the smallest item first
+    heap: Vec<usize>,
+    deserialize: D,
+    // the run files on disk, along with the number of items in each
+    files: Vec<(PathBuf, usize)>,
+    spilled: usize,
+    remaining: usize,
+    started: bool,
+    error: Option<io::Error>,
+}
+
+pub(crate) fn sort_external<I, S, D>(
+    mut it: I,
+    mut serialize: S,
+    deserialize: D,
+    mem_budget: usize,
+    tmp_dir: &Path,
+) -> io::Result<ExternalSort<<I::Item as ToOwned>::Owned, D>>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+    <I::Item as ToOwned>::Owned: Ord,
+    S: FnMut(&I::Item, &mut Vec<u8>),
+    D: FnMut(&[u8]) -> <I::Item as ToOwned>::Owned,
+{
+    let mut sort = ExternalSort {
+        runs: vec![],
+        heap: vec![],
+        deserialize,
+        files: vec![],
+        spilled: 0,
+        remaining: 0,
+        started: false,
+        error: None,
+    };
+
+    // items are serialized as they arrive, so that a run can be written out without serializing
+    // them again and the budget can account for their size
+    let mut arena = vec![];
+    let mut items = vec![];
+    let mut used = 0;
+    while let Some(item) = it.next() {
+        let start = arena.len();
+        serialize(item, &mut arena);
+        let end = arena.len();
+        items.push((item.to_owned(), start, end));
+        sort.remaining += 1;
+
+        used += end - start + mem::size_of::<(<I::Item as ToOwned>::Owned, usize, usize)>();
+        if used >= mem_budget {
+            sort.spill(&mut items, &arena, tmp_dir)?;
+            arena.clear();
+            used = 0;
+        }
+    }
+
+    while sort.files.len() > FAN_IN {
+        sort.merge_pass(tmp_dir)?;
+    }
+    for &(ref path, len) in &sort.files {
+        sort.runs.push(Run {
+            source: Source::File(open_run(path)?),
+            item: None,
+            unread: len,
+        });
+    }
+
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+    let items = items.into_iter().map(|i| i.0).collect::<Vec<_>>();
+    sort.runs.push(Run {
+        unread: items.len(),
+        source: Source::Memory(items.into_iter()),
+        item: None,
+    });
+
+    Ok(sort)
+}
+
+impl<T, D> ExternalSort<T, D> {
+    /// Returns the number of sorted runs which were written to temporary files.
+    #[inline]
+    pub fn spilled_runs(&self) -> usize {
+        self.spilled
+    }
+
+    /// Returns the error which ended reading back a sorted run, if any.
+    ///
+    /// Any items remaining in that run are not yielded.
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error which ended reading back a sorted run, if any, leaving `None` in its place.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+impl<T, D> ExternalSort<T, D>
+where
+    T: Ord,
+    D: FnMut(&[u8]) -> T,
+{
+    fn spill(
+        &mut self,
+        items: &mut Vec<(T, usize, usize)>,
+        arena: &[u8],
+        tmp_dir: &Path,
+    ) -> io::Result<()> {
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let (path, file) = create_run(tmp_dir)?;
+        self.files.push((path, items.len()));
+        self.spilled += 1;
+
+        let mut writer = BufWriter::new(file);
+        for (_, start, end) in items.drain(..) {
+            write_len(&mut writer, end - start)?;
+            writer.write_all(&arena[start..end])?;
+        }
+        writer.flush()
+    }
+
+    /// Merges the run files in groups of `FAN_IN`, replacing each group with a single run.
+    fn merge_pass(&mut self, tmp_dir: &Path) -> io::Result<()> {
+        let n = self.files.len();
+        for start in (0..n).step_by(FAN_IN) {
+            let end = cmp::min(start + FAN_IN, n);
+            let (path, file) = create_run(tmp_dir)?;
+            self.files.push((path, 0));
+            let len = merge_files(&self.files[start..end], &mut self.deserialize, file)?;
+            self.files.last_mut().unwrap().1 = len;
+        }
+        // the merged runs follow their inputs, so the order of runs is kept
+        for (path, _) in self.files.drain(..n) {
+            let _ = fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    fn fill(&mut self, i: usize) {
+        let run = &mut self.runs[i];
+        run.item = match run.source {
+            Source::File(ref mut frames) => {
+                frames.advance();
+                let item = frames.get().map(&mut self.deserialize);
+                if let Some(e) = frames.take_error() {
+                    self.error = Some(e);
+                }
+                item
+            }
+            Source::Memory(ref mut items) => items.next(),
+        };
+        if run.item.is_some() {
+            run.unread -= 1;
+        } else {
+            // the rest of a run which failed to be read back is never yielded
+            self.remaining -= run.unread;
+            run.unread = 0;
+        }
+    }
+
+    fn less(&self, a: usize, b: usize) -> bool {
+        match self.runs[a].item.cmp(&self.runs[b].item) {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            // earlier runs hold earlier items
+            Ordering::Equal => a < b,
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let left = 2 * i + 1;
+            let right = left + 1;
+            let mut smallest = i;
+            if left < self.heap.len() && self.less(self.heap[left], self.heap[smallest]) {
+                smallest = left;
+            }
+            if right < self.heap.len() && self.less(self.heap[right], self.heap[smallest]) {
+                smallest = right;
+            }
+            if smallest == i {
+                return;
+            }
+            self.heap.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+
+impl<T, D> StreamingIterator for ExternalSort<T, D>
+where
+    T: Ord,
+    D: FnMut(&[u8]) -> T,
+{
+    type Item = T;
+
+    #[inline]
+    fn advance(&mut self) {
+        if !self.started {
+            self.started = true;
+            for i in 0..self.runs.len() {
+                self.fill(i);
+                if self.runs[i].item.is_some() {
+                    self.heap.push(i);
+                }
+            }
+            for i in (0..self.heap.len() / 2).rev() {
+                self.sift_down(i);
+            }
+        } else if let Some(&top) = self.heap.first() {
+            self.remaining -= 1;
+            self.fill(top);
+            if self.runs[top].item.is_none() {
+                self.heap.swap_remove(0);
+            }
+            self.sift_down(0);
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&T> {
+        if self.started {
+            self.heap.first().and_then(|&i| self.runs[i].item.as_ref())
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = if self.started && !self.heap.is_empty() {
+            self.remaining - 1
+        } else if self.started {
+            0
+        } else {
+            self.remaining
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, D> Drop for ExternalSort<T, D> {
+    fn drop(&mut self) {
+        // close the files before removing them
+        self.runs.clear();
+        for (path, _) in &self.files {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Merges sorted run files into `out`, returning the number of items written.
+fn merge_files<T, D>(runs: &[(PathBuf, usize)], deserialize: &mut D, out: File) -> io::Result<usize>
+where
+    T: Ord,
+    D: FnMut(&[u8]) -> T,
+{
+    fn read<T, D>(
+        frames: &mut FramedRecords<BufReader<File>>,
+        deserialize: &mut D,
+    ) -> io::Result<Option<T>>
+    where
+        D: FnMut(&[u8]) -> T,
+    {
+        frames.advance();
+        match frames.take_error() {
+            Some(e) => Err(e),
+            None => Ok(frames.get().map(deserialize)),
+        }
+    }
+
+    let mut sources = vec![];
+    // ties are broken by the index of the run, since earlier runs hold earlier items
+    let mut heap = BinaryHeap::new();
+    for (i, (path, _)) in runs.iter().enumerate() {
+        let mut frames = open_run(path)?;
+        if let Some(item) = read(&mut frames, deserialize)? {
+            heap.push(Reverse((item, i)));
+        }
+        sources.push(frames);
+    }
+
+    let mut writer = BufWriter::new(out);
+    let mut len = 0;
+    while let Some(Reverse((_, i))) = heap.pop() {
+        // the frame of the item is still current in its source
+        let frame = sources[i].get().unwrap();
+        write_len(&mut writer, frame.len())?;
+        writer.write_all(frame)?;
+        len += 1;
+        if let Some(item) = read(&mut sources[i], deserialize)? {
+            heap.push(Reverse((item, i)));
+        }
+    }
+    writer.flush()?;
+    Ok(len)
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::io;
+    use std::process;
+
+    use super::super::*;
+
+    #[test]
+    fn sort_external() {
+        let dir = env::temp_dir().join(format!("streaming-iterator-sort-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+
+        let mut x = 12345u32;
+        let items = (0..1000)
+            .map(|_| {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                x % 500
+            })
+            .collect::<Vec<_>>();
+
+        let mut sorted = convert(items.iter().cloned())
+            .sort_external(
+                |i, buf| buf.extend_from_slice(&i.to_le_bytes()),
+                |buf| u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+                256,
+                &dir,
+            )
+            .unwrap();
+        assert!(sorted.spilled_runs() > 50);
+        // the runs have been merged down to a number which can be read at once
+        let files = fs::read_dir(&dir).unwrap().count();
+        assert!(files > 1 && files <= super::FAN_IN);
+        assert_eq!(sorted.size_hint(), (1000, Some(1000)));
+        assert_eq!(sorted.next(), Some(&0));
+        assert_eq!(sorted.size_hint(), (999, Some(999)));
+
+        let mut expected = items.clone();
+        expected.sort();
+        let mut actual = vec![0];
+        while let Some(&i) = sorted.next() {
+            actual.push(i);
+        }
+        assert_eq!(actual, expected);
+        assert_eq!(sorted.size_hint(), (0, Some(0)));
+        assert!(sorted.error().is_none());
+
+        drop(sorted);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn sort_external_error() {
+        let dir = env::temp_dir().join(format!("streaming-iterator-sort-error-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+
+        let mut sorted = convert(0..100u32)
+            .sort_external(
+                |i, buf| buf.extend_from_slice(&i.to_le_bytes()),
+                |buf| u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+                256,
+                &dir,
+            )
+            .unwrap();
+        // cut a run file off in the middle of its first frame
+        let path = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_len(2)
+            .unwrap();
+
+        sorted.advance();
+        let remaining = sorted.size_hint();
+        assert!(remaining.0 < 99);
+        let mut count = 0;
+        while sorted.next().is_some() {
+            count += 1;
+        }
+        assert_eq!(remaining, (count, Some(count)));
+        assert_eq!(
+            sorted.take_error().map(|e| e.kind()),
+            Some(io::ErrorKind::UnexpectedEof)
+        );
+
+        drop(sorted);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sort_in_memory() {
+        let words = ["pear", "apple", "fig", "apple"];
+        let sorted = convert_ref(words.iter().cloned())
+            .sort_external(
+                |s, buf| buf.extend_from_slice(s.as_bytes()),
+                |buf| String::from_utf8(buf.to_vec()).unwrap(),
+                1 << 20,
+                &env::temp_dir(),
+            )
+            .unwrap();
+        assert_eq!(sorted.spilled_runs(), 0);
+        assert_eq!(
+            sorted.cloned().collect::<Vec<_>>(),
+            ["apple", "apple", "fig", "pear"]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Conformance checks for implementations of `StreamingIterator`.
+//!
+//! These functions exercise an iterator through each of the trait's methods and panic with a
+//! description of the first disagreement with an expected sequence of elements, so they can be
+//! called directly from a `#[test]` function.
+//!
+//! ```
+//! # use streaming_iterator::{convert, testing};
+//! let items = [1, 2, 3];
+//! testing::check_double_ended(convert(items.iter().cloned()), &items);
+//! ```
+//!
+//! Requires the `testing` feature.
+
+use core::borrow::Borrow;
+use core::fmt::Debug;
+
+use super::{DoubleEndedStreamingIterator, StreamingIterator};
+
+/// Checks that a streaming iterator yields exactly the elements of `expected`.
+///
+/// Each check starts from a clone of `it`:
+///
+/// * `advance` followed by `get` yields each expected element, `get` returns the same element
+///   until the next `advance`, and `get` returns `None` once the iterator is exhausted.
+/// * `size_hint` bounds the number of remaining elements before each step.
+/// * `fold` visits the same elements as manual iteration.
+/// * `count` returns the number of expected elements.
+/// * `nth(n)` returns the element at index `n`, and the iterator continues from the next one.
+/// * Once fused, the iterator keeps returning `None` after its end.
+///
+/// # Panics
+///
+/// Panics if any check fails, or if the iterator breaks the `advance`/`get` protocol in a way
+/// detected by `StreamingIterator::checked`.
+pub fn check<I, E>(it: I, expected: &[E])
+where
+    I: StreamingIterator + Clone,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    check_next(it.clone(), expected);
+    check_fold(it.clone(), expected);
+    check_count(it.clone(), expected);
+    check_nth(&it, expected);
+    check_fuse(it, expected);
+}
+
+/// Checks that a double ended streaming iterator yields exactly the elements of `expected`.
+///
+/// In addition to the checks made by `check`:
+///
+/// * `advance_back` followed by `get` yields the expected elements in reverse.
+/// * `rfold` visits the same elements as iteration with `next_back`.
+/// * Alternating between `next` and `next_back` meets in the middle without skipping or
+///   repeating elements.
+///
+/// # Panics
+///
+/// Panics if any check fails.
+pub fn check_double_ended<I, E>(it: I, expected: &[E])
+where
+    I: DoubleEndedStreamingIterator + Clone,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    check(it.clone(), expected);
+    check_next_back(it.clone(), expected);
+    check_rfold(it.clone(), expected);
+    check_alternating(it, expected);
+}
+
+fn check_hint<I>(method: &str, it: &I, remaining: usize)
+where
+    I: StreamingIterator,
+{
+    let (lower, upper) = it.size_hint();
+    let too_low = match upper {
+        Some(upper) => upper < remaining,
+        None => false,
+    };
+    if lower > remaining || too_low {
+        panic!(
+            "{}: `size_hint` returned ({}, {:?}) for a remaining length of {}",
+            method, lower, upper, remaining
+        );
+    }
+}
+
+fn check_item<T, E>(method: &str, index: usize, actual: Option<&T>, expected: Option<&E>)
+where
+    T: ?Sized + PartialEq + Debug,
+    E: Borrow<T>,
+{
+    let expected = expected.map(Borrow::borrow);
+    if actual != expected {
+        panic!(
+            "{}: expected {:?} at index {}, found {:?}",
+            method, expected, index, actual
+        );
+    }
+}
+
+fn check_next<I, E>(it: I, expected: &[E])
+where
+    I: StreamingIterator,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    let mut it = it.checked();
+    for (i, item) in expected.iter().enumerate() {
+        check_hint("next", &it, expected.len() - i);
+        it.advance();
+        check_item("next", i, it.get(), Some(item));
+        check_item("get", i, it.get(), Some(item));
+    }
+    check_hint("next", &it, 0);
+    it.advance();
+    check_item::<_, E>("next", expected.len(), it.get(), None);
+    check_item::<_, E>("get", expected.len(), it.get(), None);
+}
+
+fn check_next_back<I, E>(it: I, expected: &[E])
+where
+    I: DoubleEndedStreamingIterator,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    let mut it = it.checked();
+    for (i, item) in expected.iter().enumerate().rev() {
+        check_hint("next_back", &it, i + 1);
+        it.advance_back();
+        check_item("next_back", i, it.get(), Some(item));
+        check_item("get", i, it.get(), Some(item));
+    }
+    check_hint("next_back", &it, 0);
+    it.advance_back();
+    check_item::<_, E>("next_back", 0, it.get(), None);
+}
+
+fn check_fold<I, E>(it: I, expected: &[E])
+where
+    I: StreamingIterator,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    let len = it.fold(0, |i, item| {
+        check_item("fold", i, Some(item), expected.get(i));
+        i + 1
+    });
+    check_item::<_, E>("fold", len, None, expected.get(len));
+}
+
+fn check_rfold<I, E>(it: I, expected: &[E])
+where
+    I: DoubleEndedStreamingIterator,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    let len = it.rfold(0, |n, item| {
+        let i = expected.len().wrapping_sub(n + 1);
+        check_item("rfold", i, Some(item), expected.get(i));
+        n + 1
+    });
+    if len != expected.len() {
+        panic!(
+            "rfold: visited {} elements, expected {}",
+            len,
+            expected.len()
+        );
+    }
+}
+
+fn check_count<I, E>(it: I, expected: &[E])
+where
+    I: StreamingIterator,
+{
+    let count = it.count();
+    if count != expected.len() {
+        panic!("count: returned {}, expected {}", count, expected.len());
+    }
+}
+
+fn check_nth<I, E>(it: &I, expected: &[E])
+where
+    I: StreamingIterator + Clone,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    for n in 0..=expected.len() {
+        let mut it = it.clone();
+        check_item("nth", n, it.nth(n), expected.get(n));
+        if n < expected.len() {
+            check_item("next after nth", n + 1, it.next(), expected.get(n + 1));
+        }
+    }
+}
+
+fn check_fuse<I, E>(it: I, expected: &[E])
+where
+    I: StreamingIterator,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    let mut it = it.fuse();
+    for (i, item) in expected.iter().enumerate() {
+        check_item("fuse", i, it.next(), Some(item));
+    }
+    for _ in 0..2 {
+        check_item::<_, E>("fuse", expected.len(), it.next(), None);
+        check_hint("fuse", &it, 0);
+    }
+}
+
+fn check_alternating<I, E>(it: I, expected: &[E])
+where
+    I: DoubleEndedStreamingIterator,
+    I::Item: PartialEq + Debug,
+    E: Borrow<I::Item>,
+{
+    let mut it = it.checked();
+    let (mut front, mut back) = (0, expected.len());
+    let mut from_front = true;
+    while front < back {
+        check_hint("alternating", &it, back - front);
+        if from_front {
+            check_item("next", front, it.next(), expected.get(front));
+            front += 1;
+        } else {
+            back -= 1;
+            check_item("next_back", back, it.next_back(), expected.get(back));
+        }
+        from_front = !from_front;
+    }
+    check_item::<_, E>("next", front, it.next(), None);
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn conforming() {
+        let items = [0, 1, 2, 3];
+        check_double_ended(convert(items.iter().cloned()), &items);
+        check(
+            convert(items.iter().cloned()).skip_while(|&i| i < 2),
+            &[2, 3],
+        );
+        check(convert(items.iter().cloned()).skip(3), &[3]);
+        check::<_, i32>(empty::<i32>(), &[]);
+
+        let words = ["a", "bc"];
+        check_double_ended(convert_ref(words.iter()), &words);
+    }
+
+    #[test]
+    #[should_panic(expected = "next: expected Some(2) at index 1, found Some(1)")]
+    fn wrong_element() {
+        let items = [0, 1];
+        check(convert(items.iter().cloned()), &[0, 2]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "next: `size_hint` returned (2, Some(2)) for a remaining length of 1"
+    )]
+    fn wrong_length() {
+        let items = [0, 1];
+        check(convert(items.iter().cloned()), &[0]);
+    }
+
+    #[derive(Clone)]
+    struct BadFold<I>(I);
+
+    impl<I: StreamingIterator> StreamingIterator for BadFold<I> {
+        type Item = I::Item;
+
+        fn advance(&mut self) {
+            self.0.advance()
+        }
+
+        fn get(&self) -> Option<&I::Item> {
+            self.0.get()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.0.size_hint()
+        }
+
+        fn fold<Acc, F>(mut self, init: Acc, f: F) -> Acc
+        where
+            F: FnMut(Acc, &I::Item) -> Acc,
+        {
+            self.0.advance();
+            self.0.fold(init, f)
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "fold: expected Some(0) at index 0, found Some(1)")]
+    fn inconsistent_fold() {
+        let items = [0, 1];
+        check(BadFold(convert(items.iter().cloned())), &items);
+    }
+}
@@ -0,0 +1,684 @@
+//! Streaming iterators which move elements between threads.
+
+use std::any::Any;
+use std::borrow::Borrow;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, SendError, Sender, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use super::group::push_owned;
+use super::StreamingIterator;
+
+/// A streaming iterator which advances another streaming iterator on a background thread,
+/// prefetching its elements into a fixed set of buffers.
+///
+/// Requires the `std` feature.
+pub struct Prefetch<T>
+where
+    T: ?Sized + ToOwned,
+{
+    // `None` once the background thread has been shut down
+    full: Option<Receiver<T::Owned>>,
+    free: Option<Sender<Option<T::Owned>>>,
+    thread: Option<JoinHandle<()>>,
+    current: Option<T::Owned>,
+    size_hint: (usize, Option<usize>),
+}
+
+pub(crate) fn prefetch<I>(mut it: I, n_buffers: usize) -> Prefetch<I::Item>
+where
+    I: StreamingIterator + Send + 'static,
+    I::Item: ToOwned,
+    <I::Item as ToOwned>::Owned: Send + 'static,
+{
+    assert!(n_buffers != 0, "prefetch requires at least one buffer");
+
+    let (full_tx, full_rx) = mpsc::channel();
+    let (free_tx, free_rx) = mpsc::channel::<Option<<I::Item as ToOwned>::Owned>>();
+    for _ in 0..n_buffers {
+        free_tx.send(None).unwrap();
+    }
+
+    let size_hint = it.size_hint();
+    let thread = thread::spawn(move || {
+        // a buffer is only recycled once the consumer has moved past it, and the thread exits once
+        // the consumer hangs up
+        while let Ok(buf) = free_rx.recv() {
+            let item = match it.next() {
+                Some(item) => item,
+                None => return,
+            };
+            let buf = match buf {
+                Some(mut buf) => {
+                    item.clone_into(&mut buf);
+                    buf
+                }
+                None => item.to_owned(),
+            };
+            if full_tx.send(buf).is_err() {
+                return;
+            }
+        }
+    });
+
+    Prefetch {
+        full: Some(full_rx),
+        free: Some(free_tx),
+        thread: Some(thread),
+        current: None,
+        size_hint,
+    }
+}
+
+impl<T> Prefetch<T>
+where
+    T: ?Sized + ToOwned,
+{
+    /// Waits for the background thread to exit, resuming its panic on this thread if it panicked.
+    fn join(&mut self) {
+        self.full = None;
+        self.free = None;
+        if let Some(thread) = self.thread.take() {
+            if let Err(payload) = thread.join() {
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+impl<T> StreamingIterator for Prefetch<T>
+where
+    T: ?Sized + ToOwned,
+{
+    type Item = T;
+
+    #[inline]
+    fn advance(&mut self) {
+        if let Some(buf) = self.current.take() {
+            if let Some(ref free) = self.free {
+                let _ = free.send(Some(buf));
+            }
+        }
+
+        self.current = match self.full {
+            Some(ref full) => full.recv().ok(),
+            None => None,
+        };
+        match self.current {
+            Some(_) => {
+                self.size_hint.0 = self.size_hint.0.saturating_sub(1);
+                self.size_hint.1 = self.size_hint.1.map(|n| n.saturating_sub(1));
+            }
+            None => self.join(),
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&T> {
+        self.current.as_ref().map(Borrow::borrow)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.full.is_some() {
+            self.size_hint
+        } else {
+            (0, Some(0))
+        }
+    }
+}
+
+impl<T> Drop for Prefetch<T>
+where
+    T: ?Sized + ToOwned,
+{
+    fn drop(&mut self) {
+        // hanging up stops the background thread after its current step; a panic it hits on the
+        // way out is not propagated, as this may already be unwinding
+        self.full = None;
+        self.free = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Prefetch<T>
+where
+    T: ?Sized + ToOwned + fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Prefetch")
+            .field("current", &self.get())
+            .field("size_hint", &self.size_hint)
+            .finish()
+    }
+}
+
+/// Creates a bounded channel whose receiving half is a streaming iterator and which returns each
+/// received value to a pool from which the sending half can reuse it.
+///
+/// Up to `capacity` values may be queued before `RecyclingSender::send` blocks. Values are
+/// returned to the pool when the receiver advances past them, and `RecyclingSender::acquire`
+/// takes a value from the pool, or creates one with `Default` if the pool is empty. Recycled
+/// values keep their contents, so a sender will usually clear a buffer before refilling it.
+///
+/// Requires the `std` feature.
+///
+/// ```
+/// # use streaming_iterator::{StreamingIterator, recycling_channel};
+/// # use std::thread;
+/// let (tx, mut rx) = recycling_channel::<Vec<u8>>(2);
+/// let producer = thread::spawn(move || {
+///     for i in 0..10 {
+///         let mut buf = tx.acquire();
+///         buf.clear();
+///         buf.extend_from_slice(&[i; 1024]);
+///         tx.send(buf).unwrap();
+///     }
+/// });
+/// let mut total = 0;
+/// while let Some(buf) = rx.next() {
+///     total += buf.len();
+/// }
+/// producer.join().unwrap();
+/// assert_eq!(total, 10 * 1024);
+/// ```
+pub fn recycling_channel<T>(capacity: usize) -> (RecyclingSender<T>, RecyclingReceiver<T>) {
+    let (tx, rx) = mpsc::sync_channel(capacity);
+    let pool = Arc::new(Mutex::new(vec![]));
+    let sender = RecyclingSender {
+        tx,
+        pool: pool.clone(),
+    };
+    let receiver = RecyclingReceiver {
+        rx,
+        pool,
+        current: None,
+    };
+    (sender, receiver)
+}
+
+#[inline]
+fn lock<T>(pool: &Mutex<Vec<T>>) -> MutexGuard<'_, Vec<T>> {
+    // the pool holds no invariants a panic could break
+    pool.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The sending half of a channel created by `recycling_channel`.
+///
+/// Requires the `std` feature.
+pub struct RecyclingSender<T> {
+    tx: SyncSender<T>,
+    pool: Arc<Mutex<Vec<T>>>,
+}
+
+impl<T> RecyclingSender<T> {
+    /// Sends a value to the receiver, blocking while the channel is full.
+    ///
+    /// Returns the value if the receiver has been dropped.
+    #[inline]
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.tx.send(value)
+    }
+
+    /// Takes a value which the receiver has finished with from the pool, if there is one.
+    #[inline]
+    pub fn try_acquire(&self) -> Option<T> {
+        lock(&self.pool).pop()
+    }
+}
+
+impl<T> RecyclingSender<T>
+where
+    T: Default,
+{
+    /// Takes a value which the receiver has finished with from the pool, or creates a new one if
+    /// there is none.
+    #[inline]
+    pub fn acquire(&self) -> T {
+        self.try_acquire().unwrap_or_default()
+    }
+}
+
+impl<T> Clone for RecyclingSender<T> {
+    #[inline]
+    fn clone(&self) -> RecyclingSender<T> {
+        RecyclingSender {
+            tx: self.tx.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for RecyclingSender<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("RecyclingSender").finish()
+    }
+}
+
+/// The receiving half of a channel created by `recycling_channel`, which yields values until every
+/// sender has been dropped.
+///
+/// Requires the `std` feature.
+pub struct RecyclingReceiver<T> {
+    rx: Receiver<T>,
+    pool: Arc<Mutex<Vec<T>>>,
+    current: Option<T>,
+}
+
+impl<T> StreamingIterator for RecyclingReceiver<T> {
+    type Item = T;
+
+    #[inline]
+    fn advance(&mut self) {
+        if let Some(value) = self.current.take() {
+            lock(&self.pool).push(value);
+        }
+        self.current = self.rx.recv().ok();
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&T> {
+        self.current.as_ref()
+    }
+}
+
+impl<T> fmt::Debug for RecyclingReceiver<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("RecyclingReceiver")
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+struct Batch<T, B> {
+    seq: usize,
+    // slots past `len` are kept to reuse their allocations
+    items: Vec<T>,
+    len: usize,
+    results: Vec<B>,
+}
+
+type Message<T, B> = Result<Batch<T, B>, Box<dyn Any + Send>>;
+
+pub(crate) fn par_map_batched<I, B, F, C, R>(
+    it: I,
+    batch_size: usize,
+    threads: usize,
+    f: F,
+    consume: C,
+) -> R
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+    <I::Item as ToOwned>::Owned: Send,
+    B: Send,
+    F: Fn(&I::Item) -> B + Sync,
+    C: FnOnce(&mut ParMapBatched<I, B>) -> R,
+{
+    assert!(batch_size != 0, "batches must hold at least one element");
+    assert!(threads != 0, "at least one worker thread is required");
+
+    let (jobs_tx, jobs_rx) = mpsc::channel();
+    let (results_tx, results_rx) = mpsc::channel();
+    let jobs_rx = Mutex::new(jobs_rx);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let jobs = &jobs_rx;
+            let results = results_tx.clone();
+            let f = &f;
+            scope.spawn(move || work(jobs, results, f));
+        }
+        drop(results_tx);
+
+        let mut results = ParMapBatched {
+            it,
+            batch_size,
+            jobs: jobs_tx,
+            results: results_rx,
+            max_in_flight: threads * 2,
+            free: vec![],
+            pending: vec![],
+            current: None,
+            index: 0,
+            sent: 0,
+            received: 0,
+            queued: 0,
+            source_done: false,
+        };
+        consume(&mut results)
+    })
+}
+
+fn work<T, U, B, F>(jobs: &Mutex<Receiver<Batch<T, B>>>, results: Sender<Message<T, B>>, f: &F)
+where
+    T: Borrow<U>,
+    U: ?Sized,
+    F: Fn(&U) -> B,
+{
+    loop {
+        let job = jobs.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let mut batch = match job {
+            Ok(batch) => batch,
+            Err(_) => return,
+        };
+
+        // a panic is handed to the consumer, which would otherwise wait forever for this batch
+        let message = panic::catch_unwind(AssertUnwindSafe(|| {
+            batch.results.clear();
+            for item in &batch.items[..batch.len] {
+                batch.results.push(f(item.borrow()));
+            }
+        }))
+        .map(|()| batch);
+        if results.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+/// A streaming iterator over the results of `StreamingIterator::par_map_batched`, in the order of
+/// the elements they were computed from.
+///
+/// Requires the `std` feature.
+pub struct ParMapBatched<I, B>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    it: I,
+    batch_size: usize,
+    jobs: Sender<Batch<<I::Item as ToOwned>::Owned, B>>,
+    results: Receiver<Message<<I::Item as ToOwned>::Owned, B>>,
+    max_in_flight: usize,
+    free: Vec<Batch<<I::Item as ToOwned>::Owned, B>>,
+    // batches which finished ahead of an earlier one
+    pending: Vec<Batch<<I::Item as ToOwned>::Owned, B>>,
+    current: Option<Batch<<I::Item as ToOwned>::Owned, B>>,
+    index: usize,
+    sent: usize,
+    received: usize,
+    // the number of elements in batches sent to the workers and not yet received back
+    queued: usize,
+    source_done: bool,
+}
+
+impl<I, B> ParMapBatched<I, B>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    /// Sends batches to the workers until enough are in flight or the source is exhausted.
+    fn fill(&mut self) {
+        while !self.source_done && self.sent - self.received < self.max_in_flight {
+            let mut batch = self.free.pop().unwrap_or_else(|| Batch {
+                seq: 0,
+                items: vec![],
+                len: 0,
+                results: vec![],
+            });
+            batch.seq = self.sent;
+            batch.len = 0;
+            while batch.len < self.batch_size {
+                match self.it.next() {
+                    Some(item) => push_owned(&mut batch.items, &mut batch.len, item),
+                    None => {
+                        self.source_done = true;
+                        break;
+                    }
+                }
+            }
+
+            if batch.len == 0 {
+                self.free.push(batch);
+                return;
+            }
+            self.queued += batch.len;
+            self.sent += 1;
+            // the workers outlive this iterator, so the channel stays open
+            let _ = self.jobs.send(batch);
+        }
+    }
+
+    /// Returns the next batch of results in order, or `None` once every batch has been received.
+    fn receive(&mut self) -> Option<Batch<<I::Item as ToOwned>::Owned, B>> {
+        if self.received == self.sent {
+            return None;
+        }
+        loop {
+            let seq = self.received;
+            if let Some(i) = self.pending.iter().position(|b| b.seq == seq) {
+                let batch = self.pending.swap_remove(i);
+                self.received += 1;
+                self.queued -= batch.len;
+                return Some(batch);
+            }
+            match self.results.recv() {
+                Ok(Ok(batch)) => self.pending.push(batch),
+                Ok(Err(payload)) => panic::resume_unwind(payload),
+                Err(_) => unreachable!("worker threads exited early"),
+            }
+        }
+    }
+}
+
+impl<I, B> StreamingIterator for ParMapBatched<I, B>
+where
+    I: StreamingIterator,
+    I::Item: ToOwned,
+{
+    type Item = B;
+
+    #[inline]
+    fn advance(&mut self) {
+        if let Some(ref current) = self.current {
+            if self.index + 1 < current.results.len() {
+                self.index += 1;
+                return;
+            }
+        }
+        if let Some(batch) = self.current.take() {
+            self.free.push(batch);
+        }
+
+        self.fill();
+        self.current = self.receive();
+        self.index = 0;
+        self.fill();
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&B> {
+        self.current.as_ref().map(|b| &b.results[self.index])
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let current = self
+            .current
+            .as_ref()
+            .map_or(0, |b| b.results.len() - self.index - 1);
+        let buffered = current + self.queued;
+        if self.source_done {
+            (buffered, Some(buffered))
+        } else {
+            let (lower, upper) = self.it.size_hint();
+            (
+                buffered.saturating_add(lower),
+                upper.and_then(|n| n.checked_add(buffered)),
+            )
+        }
+    }
+}
+
+impl<I, B> fmt::Debug for ParMapBatched<I, B>
+where
+    I: StreamingIterator + fmt::Debug,
+    I::Item: ToOwned,
+    B: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ParMapBatched")
+            .field("it", &self.it)
+            .field("current", &self.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::super::*;
+
+    #[test]
+    fn prefetch() {
+        let mut it = convert(0..100).prefetch(3);
+        assert_eq!(it.size_hint(), (100, Some(100)));
+        for i in 0..100 {
+            assert_eq!(it.next(), Some(&i));
+        }
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next(), None);
+
+        static WORDS: [&str; 3] = ["a", "bb", "ccc"];
+        let it = convert_ref(WORDS.iter().cloned()).prefetch(1);
+        assert_eq!(it.fold(String::new(), |acc, w| acc + w), "abbccc");
+    }
+
+    #[test]
+    fn prefetch_drop() {
+        let advanced = Arc::new(AtomicUsize::new(0));
+        let counter = advanced.clone();
+        let mut it = convert((0..).inspect(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }))
+        .prefetch(2);
+        assert_eq!(it.next(), Some(&0));
+        drop(it);
+
+        // the background thread has exited, having filled at most every buffer once more
+        let n = advanced.load(Ordering::SeqCst);
+        assert!(n <= 3);
+        assert_eq!(advanced.load(Ordering::SeqCst), n);
+    }
+
+    #[test]
+    #[should_panic(expected = "source failed")]
+    fn prefetch_panic() {
+        let mut it = convert((0..5).inspect(|&i| {
+            if i == 3 {
+                panic!("source failed");
+            }
+        }))
+        .prefetch(2);
+        while it.next().is_some() {}
+    }
+
+    #[test]
+    fn recycling_channel() {
+        let (tx, mut rx) = super::recycling_channel::<Vec<u8>>(2);
+        assert!(tx.try_acquire().is_none());
+
+        let buf = tx.acquire();
+        assert!(buf.is_empty());
+        tx.send(Vec::with_capacity(100)).unwrap();
+        tx.send(vec![1, 2]).unwrap();
+        assert_eq!(rx.next().map(Vec::capacity), Some(100));
+        assert!(tx.try_acquire().is_none());
+
+        // moving past a value returns it to the pool
+        assert_eq!(rx.next(), Some(&vec![1, 2]));
+        assert_eq!(tx.acquire().capacity(), 100);
+
+        drop(tx);
+        assert_eq!(rx.next(), None);
+        assert_eq!(rx.next(), None);
+    }
+
+    #[test]
+    fn recycling_channel_threads() {
+        let (tx, mut rx) = super::recycling_channel(1);
+        let producers = (0..2)
+            .map(|_| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..50 {
+                        let mut buf: String = tx.acquire();
+                        buf.clear();
+                        buf.push_str(&i.to_string());
+                        tx.send(buf).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(tx);
+
+        let mut sum = 0;
+        while let Some(s) = rx.next() {
+            sum += s.parse::<i32>().unwrap();
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(sum, 2 * (0..50).sum::<i32>());
+    }
+
+    #[test]
+    fn par_map_batched() {
+        let items = (0..1000).collect::<Vec<u64>>();
+        let squares = convert(items.iter().cloned()).par_map_batched(
+            7,
+            4,
+            |&i| i * i,
+            |results| {
+                assert_eq!(results.size_hint(), (1000, Some(1000)));
+                let mut squares = vec![];
+                while let Some(&s) = results.next() {
+                    squares.push(s);
+                }
+                assert_eq!(results.size_hint(), (0, Some(0)));
+                assert_eq!(results.next(), None);
+                squares
+            },
+        );
+        assert_eq!(squares, items.iter().map(|i| i * i).collect::<Vec<_>>());
+
+        // stopping early shuts the workers down
+        let first = convert(0..).par_map_batched(3, 2, |&i: &u32| i + 1, |r| r.next().cloned());
+        assert_eq!(first, Some(1));
+    }
+
+    #[test]
+    fn par_for_each_batched() {
+        let words = ["a", "bb", "ccc", "dddd", "eeeee"];
+        let total = AtomicUsize::new(0);
+        convert_ref(words.iter().cloned()).par_for_each_batched(2, 3, |w| {
+            total.fetch_add(w.len(), Ordering::SeqCst);
+        });
+        assert_eq!(total.load(Ordering::SeqCst), 15);
+
+        let empty: [u8; 0] = [];
+        convert(empty.iter()).par_for_each_batched(1, 1, |_| panic!());
+    }
+
+    #[test]
+    #[should_panic(expected = "worker failed")]
+    fn par_map_batched_panic() {
+        convert(0..100).par_for_each_batched(5, 2, |&i| {
+            if i == 42 {
+                panic!("worker failed");
+            }
+        });
+    }
+}
@@ -0,0 +1,551 @@
+//! A streaming iterator over a directory tree.
+
+use std::cmp::Ordering;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs::{self, FileType, ReadDir};
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use super::StreamingIterator;
+
+/// Creates a streaming iterator over the directory tree rooted at `root`.
+///
+/// The root itself is yielded first, at depth 0, followed by the contents of each directory
+/// directly after the directory itself. All entries share a single `PathBuf`, which has components
+/// pushed and popped as the walk moves through the tree.
+///
+/// Symbolic links are not followed by default, except for `root` itself. An I/O error reading
+/// `root` ends iteration and is reported by `WalkDir::error`. A directory whose contents cannot be
+/// read is still yielded, but the walk then continues with its siblings, and the error is recorded
+/// along with the directory's path in `WalkDir::skipped`.
+///
+/// Requires the `std` feature.
+///
+/// ```no_run
+/// # use streaming_iterator::{StreamingIterator, walk_dir};
+/// let mut walk = walk_dir("src").max_depth(2).sort_by_file_name();
+/// while let Some(entry) = walk.next() {
+///     if entry.file_name() == "target" {
+///         walk.prune();
+///         continue;
+///     }
+///     println!("{}", entry.path().display());
+/// }
+/// ```
+#[inline]
+pub fn walk_dir<P>(root: P) -> WalkDir
+where
+    P: AsRef<Path>,
+{
+    WalkDir {
+        entry: WalkEntry {
+            path: root.as_ref().to_path_buf(),
+            depth: 0,
+            file_type: None,
+            link: false,
+        },
+        stack: vec![],
+        max_depth: usize::MAX,
+        follow_links: false,
+        sort: None,
+        started: false,
+        valid: false,
+        done: false,
+        prune: false,
+        error: None,
+        skipped: vec![],
+    }
+}
+
+/// An entry yielded by `WalkDir`.
+///
+/// Requires the `std` feature.
+#[derive(Debug)]
+pub struct WalkEntry {
+    path: PathBuf,
+    depth: usize,
+    file_type: Option<FileType>,
+    link: bool,
+}
+
+impl WalkEntry {
+    /// Returns the path of the entry, which starts with the root passed to `walk_dir`.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the final component of the path of the entry.
+    ///
+    /// For the root, this is the entire path if it has no final component.
+    #[inline]
+    pub fn file_name(&self) -> &OsStr {
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+    }
+
+    /// Returns the depth of the entry below the root, which has depth 0.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the file type of the entry.
+    ///
+    /// If the entry is a symbolic link which is being followed, this is the type of its target.
+    #[inline]
+    pub fn file_type(&self) -> FileType {
+        self.file_type.expect("no current entry")
+    }
+
+    /// Determines if the entry is a directory, after following symbolic links if enabled.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    /// Determines if the entry is itself a symbolic link.
+    #[inline]
+    pub fn path_is_symlink(&self) -> bool {
+        self.link
+    }
+}
+
+enum Dir {
+    Read(ReadDir),
+    Sorted(vec::IntoIter<(OsString, FileType)>),
+}
+
+struct Frame {
+    dir: Dir,
+    // the canonical path of the directory, recorded to detect loops when following links
+    canonical: Option<PathBuf>,
+}
+
+impl Frame {
+    fn next(&mut self) -> io::Result<Option<(OsString, FileType)>> {
+        match self.dir {
+            Dir::Read(ref mut dir) => match dir.next() {
+                Some(entry) => {
+                    let entry = entry?;
+                    Ok(Some((entry.file_name(), entry.file_type()?)))
+                }
+                None => Ok(None),
+            },
+            Dir::Sorted(ref mut entries) => Ok(entries.next()),
+        }
+    }
+}
+
+type SortFn = Box<dyn FnMut(&OsStr, &OsStr) -> Ordering + Send>;
+
+/// A streaming iterator over a directory tree.
+///
+/// Requires the `std` feature.
+pub struct WalkDir {
+    entry: WalkEntry,
+    stack: Vec<Frame>,
+    max_depth: usize,
+    follow_links: bool,
+    sort: Option<SortFn>,
+    started: bool,
+    valid: bool,
+    done: bool,
+    prune: bool,
+    error: Option<io::Error>,
+    skipped: Vec<(PathBuf, io::Error)>,
+}
+
+impl WalkDir {
+    /// Only yields entries at most `depth` levels below the root.
+    #[inline]
+    pub fn max_depth(mut self, depth: usize) -> WalkDir {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Follows symbolic links to directories, descending into them.
+    ///
+    /// Links which lead back to one of their ancestors are yielded but not descended into.
+    #[inline]
+    pub fn follow_links(mut self, follow: bool) -> WalkDir {
+        self.follow_links = follow;
+        self
+    }
+
+    /// Yields the contents of each directory in the order defined by a comparison of file names.
+    ///
+    /// This requires the names of all entries in a directory to be read before the first of them
+    /// is yielded.
+    #[inline]
+    pub fn sort_by<F>(mut self, cmp: F) -> WalkDir
+    where
+        F: FnMut(&OsStr, &OsStr) -> Ordering + Send + 'static,
+    {
+        self.sort = Some(Box::new(cmp));
+        self
+    }
+
+    /// Yields the contents of each directory sorted by file name.
+    #[inline]
+    pub fn sort_by_file_name(self) -> WalkDir {
+        self.sort_by(|a, b| a.cmp(b))
+    }
+
+    /// Prevents iteration from descending into the current entry.
+    ///
+    /// This has no effect if the current entry is not a directory.
+    #[inline]
+    pub fn prune(&mut self) {
+        self.prune = true;
+    }
+
+    /// Returns the error which ended iteration, if any.
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error which ended iteration, if any, leaving `None` in its place.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Returns the directories whose contents could not be read, along with the errors
+    /// encountered, in the order they were skipped.
+    #[inline]
+    pub fn skipped(&self) -> &[(PathBuf, io::Error)] {
+        &self.skipped
+    }
+
+    /// Takes the directories skipped so far, leaving the list empty.
+    #[inline]
+    pub fn take_skipped(&mut self) -> Vec<(PathBuf, io::Error)> {
+        mem::take(&mut self.skipped)
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        let file_type = fs::symlink_metadata(&self.entry.path)?.file_type();
+        self.entry.link = file_type.is_symlink();
+        self.entry.file_type = Some(if self.entry.link {
+            fs::metadata(&self.entry.path)?.file_type()
+        } else {
+            file_type
+        });
+        self.valid = true;
+        Ok(())
+    }
+
+    fn open(&mut self) -> io::Result<Option<Frame>> {
+        let canonical = if self.follow_links {
+            let canonical = fs::canonicalize(&self.entry.path)?;
+            if self
+                .stack
+                .iter()
+                .any(|frame| frame.canonical.as_ref() == Some(&canonical))
+            {
+                return Ok(None);
+            }
+            Some(canonical)
+        } else {
+            None
+        };
+
+        let dir = fs::read_dir(&self.entry.path)?;
+        let dir = match self.sort {
+            Some(ref mut cmp) => {
+                let mut entries = dir
+                    .map(|entry| entry.and_then(|e| Ok((e.file_name(), e.file_type()?))))
+                    .collect::<io::Result<Vec<_>>>()?;
+                entries.sort_by(|a, b| cmp(&a.0, &b.0));
+                Dir::Sorted(entries.into_iter())
+            }
+            None => Dir::Read(dir),
+        };
+
+        Ok(Some(Frame { dir, canonical }))
+    }
+
+    fn skip(&mut self, error: io::Error) {
+        self.skipped.push((self.entry.path.clone(), error));
+    }
+
+    fn step(&mut self) {
+        let prune = mem::replace(&mut self.prune, false);
+        let mut descended = false;
+        if !prune && self.entry.is_dir() && self.entry.depth < self.max_depth {
+            match self.open() {
+                Ok(Some(frame)) => {
+                    // the current entry's name stays on the path while its contents are walked
+                    self.stack.push(frame);
+                    descended = true;
+                }
+                Ok(None) => {}
+                Err(e) => self.skip(e),
+            }
+        }
+        if !descended && self.entry.depth > 0 {
+            self.entry.path.pop();
+        }
+
+        loop {
+            let next = match self.stack.last_mut() {
+                Some(frame) => frame.next(),
+                None => {
+                    self.done = true;
+                    return;
+                }
+            };
+            let next = match next {
+                Ok(next) => next,
+                Err(e) => {
+                    // the path is that of the directory being read; abandon the rest of it
+                    self.skip(e);
+                    None
+                }
+            };
+
+            match next {
+                Some((name, file_type)) => {
+                    self.entry.path.push(&name);
+                    self.entry.depth = self.stack.len();
+                    self.entry.link = file_type.is_symlink();
+                    self.entry.file_type = Some(if self.entry.link && self.follow_links {
+                        // a dangling link is yielded as a link
+                        fs::metadata(&self.entry.path)
+                            .map(|m| m.file_type())
+                            .unwrap_or(file_type)
+                    } else {
+                        file_type
+                    });
+                    self.valid = true;
+                    return;
+                }
+                None => {
+                    self.stack.pop();
+                    if !self.stack.is_empty() {
+                        self.entry.path.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WalkDir {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("WalkDir")
+            .field("entry", &self.entry)
+            .field("max_depth", &self.max_depth)
+            .field("follow_links", &self.follow_links)
+            .field("sorted", &self.sort.is_some())
+            .field("error", &self.error)
+            .field("skipped", &self.skipped)
+            .finish()
+    }
+}
+
+impl StreamingIterator for WalkDir {
+    type Item = WalkEntry;
+
+    #[inline]
+    fn advance(&mut self) {
+        let valid = mem::replace(&mut self.valid, false);
+        if self.done {
+            return;
+        }
+
+        let result = if !self.started {
+            self.started = true;
+            self.start()
+        } else if valid {
+            self.step();
+            Ok(())
+        } else {
+            Ok(())
+        };
+
+        if let Err(e) = result {
+            self.done = true;
+            self.error = Some(e);
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> Option<&WalkEntry> {
+        if self.valid {
+            Some(&self.entry)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+    use std::process;
+
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path =
+                env::temp_dir().join(format!("streaming-iterator-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&path);
+            for dir in &["a/y", "c"] {
+                fs::create_dir_all(path.join(dir)).unwrap();
+            }
+            for file in &["a/x.txt", "a/y/z.txt", "b.txt"] {
+                File::create(path.join(file)).unwrap();
+            }
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn collect(walk: &mut WalkDir, root: &Path) -> Vec<(String, usize, bool)> {
+        let mut entries = vec![];
+        while let Some(entry) = walk.next() {
+            let path = entry.path().strip_prefix(root).unwrap();
+            entries.push((
+                path.to_str().unwrap().replace('\\', "/"),
+                entry.depth(),
+                entry.is_dir(),
+            ));
+        }
+        assert!(walk.error().is_none());
+        assert!(walk.skipped().is_empty());
+        entries
+    }
+
+    #[test]
+    fn sorted() {
+        let dir = TempDir::new("walk-sorted");
+        let mut walk = walk_dir(&dir.0).sort_by_file_name();
+        assert_eq!(
+            collect(&mut walk, &dir.0),
+            [
+                ("".to_owned(), 0, true),
+                ("a".to_owned(), 1, true),
+                ("a/x.txt".to_owned(), 2, false),
+                ("a/y".to_owned(), 2, true),
+                ("a/y/z.txt".to_owned(), 3, false),
+                ("b.txt".to_owned(), 1, false),
+                ("c".to_owned(), 1, true),
+            ]
+        );
+        walk.advance();
+        assert!(walk.get().is_none());
+
+        let mut walk = walk_dir(&dir.0).sort_by(|a, b| b.cmp(a)).max_depth(1);
+        let names = collect(&mut walk, &dir.0)
+            .into_iter()
+            .map(|e| e.0)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["", "c", "b.txt", "a"]);
+    }
+
+    #[test]
+    fn unsorted() {
+        let dir = TempDir::new("walk-unsorted");
+        let mut walk = walk_dir(&dir.0);
+        let mut entries = collect(&mut walk, &dir.0);
+        assert_eq!(entries.len(), 7);
+        entries.sort();
+        assert_eq!(entries[4], ("a/y/z.txt".to_owned(), 3, false));
+    }
+
+    #[test]
+    fn prune() {
+        let dir = TempDir::new("walk-prune");
+        let mut walk = walk_dir(&dir.0).sort_by_file_name();
+        let mut names = vec![];
+        while let Some(entry) = walk.next() {
+            let name = entry.file_name().to_str().unwrap().to_owned();
+            if name == "a" {
+                walk.prune();
+            }
+            names.push(name);
+        }
+        assert_eq!(&names[1..], ["a", "b.txt", "c"]);
+    }
+
+    #[test]
+    fn unreadable_dir() {
+        let dir = TempDir::new("walk-unreadable");
+        let mut walk = walk_dir(&dir.0).sort_by_file_name();
+        let mut names = vec![];
+        while let Some(entry) = walk.next() {
+            let name = entry.file_name().to_str().unwrap().to_owned();
+            if name == "a" {
+                // remove the directory before the walk reads it
+                fs::remove_dir_all(entry.path()).unwrap();
+            }
+            names.push(name);
+        }
+        assert_eq!(&names[1..], ["a", "b.txt", "c"]);
+        assert!(walk.error().is_none());
+        let skipped = walk.take_skipped();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, dir.0.join("a"));
+        assert_eq!(skipped[0].1.kind(), io::ErrorKind::NotFound);
+        assert!(walk.skipped().is_empty());
+    }
+
+    #[test]
+    fn missing_root() {
+        let mut walk = walk_dir("/this/path/does/not/exist");
+        assert!(walk.next().is_none());
+        assert_eq!(
+            walk.error().map(io::Error::kind),
+            Some(io::ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn follow_links() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new("walk-links");
+        symlink(dir.0.join("a/y"), dir.0.join("c/link")).unwrap();
+        symlink(&dir.0, dir.0.join("a/y/loop")).unwrap();
+
+        let mut walk = walk_dir(dir.0.join("c")).sort_by_file_name();
+        let entries = collect(&mut walk, &dir.0);
+        assert_eq!(
+            entries,
+            [("c".to_owned(), 0, true), ("c/link".to_owned(), 1, false)]
+        );
+
+        let mut walk = walk_dir(dir.0.join("c"))
+            .sort_by_file_name()
+            .follow_links(true);
+        let entries = collect(&mut walk, &dir.0)
+            .into_iter()
+            .map(|e| e.0)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            &entries[..4],
+            ["c", "c/link", "c/link/loop", "c/link/loop/a"]
+        );
+        assert!(!entries
+            .iter()
+            .any(|e| e.starts_with("c/link/loop/a/y/loop/")));
+    }
+}
@@ -0,0 +1,58 @@
+//! Exercises the allocating parts of the crate without the standard library.
+
+#![no_std]
+
+#[macro_use]
+extern crate alloc;
+extern crate streaming_iterator;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use streaming_iterator::{convert, convert_ref, permutations, PackedVec, StreamingIterator};
+
+#[test]
+fn owned() {
+    let words = ["a", "bc"];
+    let owned = convert_ref(words.iter().cloned())
+        .owned()
+        .collect::<Vec<String>>();
+    assert_eq!(owned, words);
+}
+
+#[test]
+fn boxed() {
+    let items = [1, 2, 3];
+    let mut it: Box<dyn StreamingIterator<Item = i32>> = Box::new(convert(items.iter().cloned()));
+    assert_eq!(it.next(), Some(&1));
+    assert_eq!(it.fold(0, |acc, &i| acc + i), 5);
+}
+
+#[test]
+fn collect() {
+    let words = ["ab", "c"];
+    let joined: String = convert_ref(words.iter().cloned()).collect();
+    assert_eq!(joined, "abc");
+    let packed: PackedVec<str> = convert_ref(words.iter().cloned()).collect();
+    assert_eq!(&packed[1], "c");
+}
+
+#[test]
+fn group_by() {
+    let items = [1, 1, 2];
+    let mut it = convert(items.iter().cloned()).group_by(|i| i);
+    let mut sizes = vec![];
+    while let Some(mut group) = it.next_group() {
+        sizes.push(group.by_ref().count());
+    }
+    assert_eq!(sizes, [2, 1]);
+}
+
+#[test]
+fn permutations_of() {
+    let mut it = permutations(vec![1, 2]);
+    assert_eq!(it.next(), Some(&[1, 2][..]));
+    assert_eq!(it.next(), Some(&[2, 1][..]));
+    assert_eq!(it.next(), None);
+}